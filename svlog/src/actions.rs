@@ -0,0 +1,277 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::Write,
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono_tz::Tz;
+use regex::Regex;
+use snafu::{ensure, ResultExt};
+use svlog_cli::Args;
+use svlog_core::{Entry, LogLine, RateMonitor, RateRule};
+use svlog_util::{
+    regex::build_regex, InvalidArgCombinationSnafu, OpenFileSnafu, RulesSnafu, SvLogError,
+    SvLogResult,
+};
+
+// Maximum number of commands waiting for a running command to finish
+const MAX_QUEUED: usize = 1000;
+
+// A command which is run for the new entries matching the regex in follow
+// mode.
+struct Rule {
+    re: Regex,
+    command: String,
+    last_run: Option<Instant>,
+}
+
+// The rules given with --on-match/--exec, --rate-alert and --rules and the
+// commands which are still running or waiting to be run.
+#[derive(Default)]
+pub struct Actions {
+    rules: Vec<Rule>,
+    rate_monitor: RateMonitor,
+    // Commands run for the alerts of the rate rules, by index of the rule
+    rate_commands: Vec<Option<String>>,
+    // Minimum time between two runs of the same rule
+    debounce: Duration,
+    max_running: usize,
+    running: Vec<Child>,
+    // Commands waiting until less than max_running commands are running
+    queued: VecDeque<Job>,
+}
+
+// A command with the environment variables and the input for a log line
+struct Job {
+    command: String,
+    env: Vec<(&'static str, String)>,
+    input: String,
+}
+
+// A rule read from a rules file
+enum FileRule {
+    Match(Rule),
+    Rate(RateRule, Option<String>),
+}
+
+impl Actions {
+    pub fn from_args(args: &Args) -> SvLogResult<Self> {
+        ensure!(
+            args.on_match.len() == args.exec.len(),
+            InvalidArgCombinationSnafu {
+                message: "every --on-match needs an --exec".to_string(),
+            }
+        );
+        let mut rules = Vec::new();
+        for (pattern, command) in args.on_match.iter().zip(&args.exec) {
+            rules.push(Rule::new(build_regex(pattern, false)?, command));
+        }
+        let mut rate_rules = args.rate_alert.clone();
+        let mut rate_commands = vec![None; rate_rules.len()];
+        if let Some(path) = &args.rules {
+            for rule in read_rules(path)? {
+                match rule {
+                    FileRule::Match(rule) => rules.push(rule),
+                    FileRule::Rate(rule, command) => {
+                        rate_rules.push(rule);
+                        rate_commands.push(command);
+                    }
+                }
+            }
+        }
+        Ok(Actions {
+            rules,
+            rate_monitor: RateMonitor::new(rate_rules),
+            rate_commands,
+            debounce: Duration::from_secs(args.debounce),
+            max_running: args.max_exec.max(1),
+            running: Vec::new(),
+            queued: VecDeque::new(),
+        })
+    }
+
+    // Runs the commands of the rules matching the log line. The entry is
+    // passed in environment variables and the formatted line on stdin. Errors
+    // are printed and don't stop following the logs.
+    pub fn run(&mut self, log_line: &LogLine, tz: &Option<Tz>, formatted: &str) {
+        let now = Instant::now();
+        for i in 0..self.rules.len() {
+            let rule = &self.rules[i];
+            let debounced =
+                matches!(rule.last_run, Some(last_run) if now - last_run < self.debounce);
+            if debounced || !rule.re.is_match(log_line.content()) {
+                continue;
+            }
+            self.rules[i].last_run = Some(now);
+            let command = self.rules[i].command.clone();
+            self.exec(&command, log_line, tz, formatted);
+        }
+    }
+
+    // Records the log line for the rate rules and returns an alert line (with
+    // the service of the log line and priority "alert") for each exceeded
+    // rule together with the command of the rule.
+    pub fn rate_alerts(&mut self, log_line: &LogLine) -> Vec<(LogLine, Option<String>)> {
+        let mut alert_lines = Vec::new();
+        for alert in self.rate_monitor.record(log_line) {
+            let alert_line = alert
+                .log_line(&self.rate_monitor.rules()[alert.rule])
+                .with_label(log_line.label().map(Arc::from));
+            alert_lines.push((alert_line, self.rate_commands[alert.rule].clone()));
+        }
+        alert_lines
+    }
+
+    // Runs the command for the log line once less than max_running commands
+    // are running. The command is queued meanwhile, without waiting for the
+    // running commands.
+    pub fn exec(&mut self, command: &str, log_line: &LogLine, tz: &Option<Tz>, formatted: &str) {
+        if self.queued.len() >= MAX_QUEUED {
+            eprintln!("Too many commands are waiting, skipping \"{}\"", command);
+        } else {
+            self.queued
+                .push_back(Job::new(command, log_line, tz, formatted));
+        }
+        self.start_queued();
+    }
+
+    // Reaps the finished commands and starts the queued commands while less
+    // than max_running commands are running. Called for every new line and
+    // from time to time while following the logs.
+    pub fn start_queued(&mut self) {
+        self.running
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+        while self.running.len() < self.max_running {
+            let job = match self.queued.pop_front() {
+                Some(job) => job,
+                None => break,
+            };
+            match job.spawn() {
+                Ok(child) => self.running.push(child),
+                Err(e) => eprintln!("Failed to run \"{}\": {}", job.command, e),
+            }
+        }
+    }
+
+    // Returns whether no command is running or queued.
+    pub fn is_idle(&self) -> bool {
+        self.running.is_empty() && self.queued.is_empty()
+    }
+}
+
+impl Rule {
+    fn new(re: Regex, command: &str) -> Self {
+        Rule {
+            re,
+            command: command.to_string(),
+            last_run: None,
+        }
+    }
+}
+
+impl Job {
+    fn new(command: &str, log_line: &LogLine, tz: &Option<Tz>, formatted: &str) -> Self {
+        let entry = Entry::new(log_line);
+        Job {
+            command: command.to_string(),
+            env: vec![
+                ("SVLOG_TIMESTAMP", log_line.format_date(tz)),
+                ("SVLOG_SERVICE", entry.service),
+                ("SVLOG_LABEL", entry.label.unwrap_or_default()),
+                ("SVLOG_FACILITY", entry.facility.unwrap_or_default()),
+                ("SVLOG_PRIORITY", entry.priority.value().to_string()),
+                ("SVLOG_MESSAGE", entry.message),
+            ],
+            input: formatted.to_string(),
+        }
+    }
+
+    fn spawn(&self) -> std::io::Result<Child> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // NOTE: commands may exit without reading stdin
+            let _ = writeln!(stdin, "{}", self.input);
+        }
+        Ok(child)
+    }
+}
+
+// Reads the rules of a file with "on-match REGEX" and "rate RULE" lines, each
+// followed by an "exec COMMAND" line (optional for rate rules). Empty lines
+// and lines starting with "#" are ignored.
+fn read_rules(path: &Path) -> SvLogResult<Vec<FileRule>> {
+    let content = fs::read_to_string(path).context(OpenFileSnafu {
+        path: path.display().to_string(),
+    })?;
+    let error = |line: usize, message: String| -> SvLogError {
+        RulesSnafu {
+            path: path.display().to_string(),
+            line,
+            message,
+        }
+        .build()
+    };
+    let mut rules = Vec::new();
+    // Rule whose "exec" line may follow
+    let mut pending: Option<FileRule> = None;
+    let mut last_line = 0;
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        last_line = i + 1;
+        let (key, value) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(key, value)| (key, value.trim()));
+        match (key, pending.take()) {
+            (_, Some(FileRule::Match(_))) if key != "exec" => {
+                return Err(error(i + 1, "missing \"exec\"".to_string()))
+            }
+            ("exec", Some(_)) if value.is_empty() => {
+                return Err(error(i + 1, "missing command".to_string()))
+            }
+            ("exec", Some(FileRule::Match(rule))) => {
+                rules.push(FileRule::Match(Rule::new(rule.re, value)))
+            }
+            ("exec", Some(FileRule::Rate(rule, _))) => {
+                rules.push(FileRule::Rate(rule, Some(value.to_string())))
+            }
+            ("exec", None) => {
+                return Err(error(i + 1, "missing \"on-match\" or \"rate\"".to_string()))
+            }
+            (key, previous) => {
+                rules.extend(previous);
+                pending = Some(match key {
+                    "on-match" => FileRule::Match(Rule::new(
+                        build_regex(value, false).map_err(|e| error(i + 1, e.to_string()))?,
+                        "",
+                    )),
+                    "rate" => FileRule::Rate(
+                        RateRule::parse(value).ok_or_else(|| {
+                            error(i + 1, format!("invalid rate rule \"{value}\""))
+                        })?,
+                        None,
+                    ),
+                    _ => return Err(error(i + 1, format!("unknown key \"{key}\""))),
+                });
+            }
+        }
+    }
+    match pending {
+        Some(FileRule::Match(_)) => Err(error(last_line, "missing \"exec\"".to_string())),
+        pending => {
+            rules.extend(pending);
+            Ok(rules)
+        }
+    }
+}
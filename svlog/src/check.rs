@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use svlog_core::{read_log_lines, LogFilterSettings, LogSource};
+use svlog_util::SvLogResult;
+
+use crate::printer::print_skipped;
+
+// Exit status and name of the states of a Nagios/Icinga plugin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckState {
+    Ok = 0,
+    Warning = 1,
+    Critical = 2,
+    Unknown = 3,
+}
+
+impl CheckState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheckState::Ok => "OK",
+            CheckState::Warning => "WARNING",
+            CheckState::Critical => "CRITICAL",
+            CheckState::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+// Counts the matching entries per service, prints the status line with the
+// counts as performance data and returns the state. A service is in the
+// WARNING or CRITICAL state if it has more entries than the threshold. Without
+// thresholds, any entry is critical.
+pub fn check(
+    sources: &[Box<dyn LogSource>],
+    log_filter_settings: &LogFilterSettings,
+    show_labels: bool,
+    warning: Option<usize>,
+    critical: Option<usize>,
+) -> SvLogResult<CheckState> {
+    let critical = match (warning, critical) {
+        (None, None) => Some(0),
+        (_, critical) => critical,
+    };
+    let (log_lines, skipped) = read_log_lines(sources, log_filter_settings)?;
+    print_skipped(&skipped);
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for log_line in &log_lines {
+        let service = match log_line.label() {
+            Some(label) if show_labels => format!("{}/{}", label, log_line.service()),
+            _ => log_line.service().to_string(),
+        };
+        *counts.entry(service).or_default() += 1;
+    }
+    let state_of = |count: usize| {
+        if matches!(critical, Some(critical) if count > critical) {
+            CheckState::Critical
+        } else if matches!(warning, Some(warning) if count > warning) {
+            CheckState::Warning
+        } else {
+            CheckState::Ok
+        }
+    };
+    let state = counts
+        .values()
+        .map(|count| state_of(*count))
+        .max()
+        .unwrap_or(CheckState::Ok);
+    println!(
+        "{}",
+        status_line(state, &counts, log_lines.len(), warning, critical)
+    );
+    Ok(state)
+}
+
+// Formats the status like "SVLOG WARNING - 6 matching entries (kernel: 6) |
+// 'kernel'=6;5;10;0 total=6;;;0".
+fn status_line(
+    state: CheckState,
+    counts: &BTreeMap<String, usize>,
+    total: usize,
+    warning: Option<usize>,
+    critical: Option<usize>,
+) -> String {
+    let summary = if counts.is_empty() {
+        "no matching entries".to_string()
+    } else {
+        let services: Vec<String> = counts
+            .iter()
+            .map(|(service, count)| format!("{service}: {count}"))
+            .collect();
+        format!("{} matching entries ({})", total, services.join(", "))
+    };
+    let threshold = |n: Option<usize>| n.map(|n| n.to_string()).unwrap_or_default();
+    let (warning, critical) = (threshold(warning), threshold(critical));
+    let mut perf_data: Vec<String> = counts
+        .iter()
+        .map(|(service, count)| {
+            // NOTE: quotes in labels are doubled in perfdata
+            let label = service.replace('\'', "''");
+            format!("'{label}'={count};{warning};{critical};0")
+        })
+        .collect();
+    perf_data.push(format!("total={total};;;0"));
+    format!(
+        "SVLOG {} - {} | {}",
+        state.name(),
+        summary,
+        perf_data.join(" ")
+    )
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use chrono::Utc;
+use svlog_cli::Args;
+use svlog_core::{
+    read_log_lines, write_bundle, BundleQuery, LogFilterSettings, LogSource, Manifest,
+    BUNDLE_VERSION,
+};
+use svlog_util::{boot_table, host_name, local_tz, SvLogResult};
+
+use crate::printer::print_skipped;
+
+// Writes the entries selected by the filters to a bundle. When a bundle is
+// exported again, the machine information is taken from its manifest.
+pub fn export(
+    args: &Args,
+    sources: &[Box<dyn LogSource>],
+    log_filter_settings: &LogFilterSettings,
+    bundle_manifest: Option<&Manifest>,
+    path: &Path,
+) -> SvLogResult<()> {
+    let (mut log_lines, skipped) = read_log_lines(sources, log_filter_settings)?;
+    print_skipped(&skipped);
+    if let Some(n) = args.lines {
+        let skip_amount = log_lines.len().saturating_sub(n);
+        log_lines = log_lines.into_iter().skip(skip_amount).collect();
+    }
+    let (hostname, timezone, boots) = match bundle_manifest {
+        Some(manifest) => (
+            manifest.hostname.clone(),
+            manifest.timezone.clone(),
+            manifest.boots.clone(),
+        ),
+        None => (
+            host_name(),
+            local_tz().name().to_string(),
+            boot_table(args.boot_source, Path::new(&args.wtmp), args.log_dir()).unwrap_or_default(),
+        ),
+    };
+    let manifest = Manifest {
+        version: BUNDLE_VERSION,
+        svlog_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: Utc::now().naive_utc(),
+        hostname,
+        timezone,
+        boots,
+        query: BundleQuery {
+            args: std::env::args().skip(1).collect(),
+            services: args.services.clone(),
+            windows: log_filter_settings.windows.clone(),
+            min_priority: log_filter_settings.min_priority.value(),
+            max_priority: log_filter_settings.max_priority.value(),
+            pattern: args.filter.clone(),
+        },
+    };
+    write_bundle(path, &log_lines, &manifest)?;
+    println!("Exported {} entries to {}", log_lines.len(), path.display());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use std::{ops::RangeInclusive, path::Path};
+
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+use regex::Regex;
+use svlog_cli::{Args, TimePoint};
+use svlog_core::{LogFilterSettings, Manifest};
+use svlog_util::{boot_table_times, boot_times, regex::build_regex, SvLogResult, TimeWindow};
+
+// Builds the filters from the command line arguments. Local times given with
+// --since and --until are converted to UTC with the timezone tz. The boots of
+// a bundle are taken from its manifest.
+pub fn log_filter_settings(
+    args: &Args,
+    tz: &Option<Tz>,
+    manifest: Option<&Manifest>,
+) -> SvLogResult<LogFilterSettings> {
+    let re: Option<Regex> = args
+        .filter
+        .as_ref()
+        .map(|pattern| build_regex(pattern, args.case_insensitive))
+        .transpose()?;
+    let wtmp_path = Path::new(&args.wtmp);
+    let to_utc = |time: TimePoint| -> SvLogResult<NaiveDateTime> {
+        match (time, tz) {
+            (TimePoint::Local(time), Some(tz)) => args.dst_policy.to_utc(tz, &time),
+            (TimePoint::Local(time), None) | (TimePoint::Utc(time), _) => Ok(time),
+        }
+    };
+    let time_window = TimeWindow::new(
+        args.since.map(to_utc).transpose()?,
+        args.until.map(to_utc).transpose()?,
+    );
+    let current_boot = [0..=0];
+    let boot_offsets: Option<&[RangeInclusive<usize>]> = if args.boot {
+        Some(&current_boot)
+    } else {
+        args.boot_offset.as_ref().map(|offsets| &offsets.0[..])
+    };
+    let windows = if let Some(boot_offsets) = boot_offsets {
+        let boot_windows = if let Some(manifest) = manifest {
+            boot_table_times(&manifest.boots, boot_offsets)?
+        } else {
+            boot_times(boot_offsets, args.boot_source, wtmp_path, args.log_dir())?
+        };
+        boot_windows
+            .iter()
+            .filter_map(|boot_window| boot_window.intersect(&time_window))
+            .collect()
+    } else {
+        vec![time_window]
+    };
+    Ok(LogFilterSettings {
+        re,
+        windows,
+        min_priority: args.priority.0,
+        max_priority: args.priority.1,
+    })
+}
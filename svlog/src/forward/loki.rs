@@ -0,0 +1,130 @@
+use std::{path::Path, sync::Arc, thread, time::Duration};
+
+use svlog_core::{loki_push_body, LogLine};
+use svlog_util::{LokiSnafu, SvLogError, SvLogResult};
+use ureq::{Agent, AgentBuilder};
+
+use crate::forward::{tls_connector, Forwarder};
+
+// Time to wait before the first retry of a failed push, doubled with each retry
+static INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+static MAX_BACKOFF: Duration = Duration::from_secs(30);
+static TIMEOUT: Duration = Duration::from_secs(30);
+
+// Pushes log lines in batches to the HTTP API of Grafana Loki. Pushes which
+// failed because of the connection, rate limits (429) or server errors (5xx)
+// are retried with exponential backoff. While following the logs, they are
+// retried until they succeed, so an outage of Loki doesn't stop shipping.
+pub struct LokiForwarder {
+    agent: Agent,
+    url: String,
+    hostname: String,
+    // Value of the X-Scope-OrgID header for multi-tenant setups
+    tenant: Option<String>,
+    batch_size: usize,
+    retries: u32,
+    // Whether the logs are followed
+    follow: bool,
+    // Lines which are pushed with the next batch
+    batch: Vec<LogLine>,
+}
+
+impl LokiForwarder {
+    pub fn new(
+        url: String,
+        hostname: String,
+        tenant: Option<String>,
+        batch_size: usize,
+        retries: u32,
+        follow: bool,
+        ca_file: Option<&Path>,
+    ) -> SvLogResult<Self> {
+        let connector = tls_connector(ca_file).map_err(|message| loki_error(&url, message))?;
+        let agent = AgentBuilder::new()
+            .tls_connector(Arc::new(connector))
+            .timeout(TIMEOUT)
+            .build();
+        Ok(LokiForwarder {
+            agent,
+            url,
+            hostname,
+            tenant,
+            batch_size: batch_size.max(1),
+            retries,
+            follow,
+            batch: Vec::new(),
+        })
+    }
+
+    fn push(&self) -> SvLogResult<()> {
+        let body = loki_push_body(&self.batch, &self.hostname).to_string();
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .agent
+                .post(&self.url)
+                .set("Content-Type", "application/json");
+            if let Some(tenant) = &self.tenant {
+                request = request.set("X-Scope-OrgID", tenant);
+            }
+            let message = match request.send_string(&body) {
+                Ok(_) => return Ok(()),
+                // NOTE: other client errors, e.g. entries which are too old,
+                // fail again when retrying
+                Err(ureq::Error::Status(status, response)) if status != 429 && status < 500 => {
+                    let text = response.into_string().unwrap_or_default();
+                    return Err(loki_error(
+                        &self.url,
+                        format!("status {}: {}", status, text.trim()),
+                    ));
+                }
+                Err(ureq::Error::Status(status, _)) => format!("status {}", status),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.retries && !self.follow {
+                return Err(loki_error(&self.url, message));
+            }
+            attempt += 1;
+            eprintln!(
+                "Failed to push to {} ({}), retrying in {:.1}s",
+                self.url,
+                message,
+                backoff.as_secs_f64()
+            );
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+impl Forwarder for LokiForwarder {
+    fn send(&mut self, log_line: &LogLine) -> SvLogResult<()> {
+        self.batch.push(log_line.clone());
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> SvLogResult<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        self.push()?;
+        self.batch.clear();
+        Ok(())
+    }
+
+    fn target(&self) -> String {
+        self.url.clone()
+    }
+}
+
+fn loki_error(url: &str, message: String) -> SvLogError {
+    LokiSnafu {
+        url: url.to_string(),
+        message,
+    }
+    .build()
+}
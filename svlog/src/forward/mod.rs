@@ -0,0 +1,48 @@
+mod loki;
+mod syslog;
+
+use std::{fs, path::Path};
+
+pub use loki::LokiForwarder;
+use native_tls::{Certificate, TlsConnector};
+use svlog_core::LogLine;
+use svlog_util::SvLogResult;
+pub use syslog::SyslogForwarder;
+
+// A server the log lines are sent to instead of printing them. It is only used
+// by the thread printing the log lines.
+pub trait Forwarder: Send {
+    // Sends a log line, possibly only with the next flush.
+    fn send(&mut self, log_line: &LogLine) -> SvLogResult<()>;
+
+    // Sends the log lines which are still buffered.
+    fn flush(&mut self) -> SvLogResult<()> {
+        Ok(())
+    }
+
+    // Called from time to time while following the logs without new lines,
+    // e.g. to notice a target which is gone.
+    fn keepalive(&mut self) -> SvLogResult<()> {
+        Ok(())
+    }
+
+    // Returns the address of the server shown in messages.
+    fn target(&self) -> String;
+}
+
+// Returns the TLS connector of the forwarders. It trusts the certificates in
+// the CA file (PEM) instead of the ones of the system if one is given.
+fn tls_connector(ca_file: Option<&Path>) -> Result<TlsConnector, String> {
+    let mut builder = TlsConnector::builder();
+    if let Some(ca_file) = ca_file {
+        builder.disable_built_in_roots(true);
+        let certificates = fs::read(ca_file)
+            .map_err(|e| e.to_string())
+            .and_then(|pem| Certificate::stack_from_pem(&pem).map_err(|e| e.to_string()))
+            .map_err(|message| format!("{}: {}", ca_file.display(), message))?;
+        for certificate in certificates {
+            builder.add_root_certificate(certificate);
+        }
+    }
+    builder.build().map_err(|e| e.to_string())
+}
@@ -0,0 +1,174 @@
+use std::{
+    io::{self, ErrorKind, Write},
+    net::{TcpStream, UdpSocket},
+    path::{Path, PathBuf},
+};
+
+use chrono_tz::Tz;
+use native_tls::TlsStream;
+use snafu::ResultExt;
+use svlog_core::{format_syslog, LogLine};
+use svlog_util::{ForwardSnafu, SvLogResult, SyslogFormat, SyslogTarget, Transport};
+
+use crate::forward::{tls_connector, Forwarder};
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+// Sends log lines to a syslog server. Connections over TCP and TLS are opened
+// again if the server closed them (checked before sending and while following
+// the logs without new lines) or once if sending fails, e.g. after the server
+// was restarted.
+pub struct SyslogForwarder {
+    target: SyslogTarget,
+    format: SyslogFormat,
+    hostname: String,
+    // Timezone of the RFC 3164 timestamps, None means UTC
+    tz: Option<Tz>,
+    ca_file: Option<PathBuf>,
+    connection: Connection,
+}
+
+impl SyslogForwarder {
+    pub fn connect(
+        target: SyslogTarget,
+        format: SyslogFormat,
+        hostname: String,
+        tz: Option<Tz>,
+        ca_file: Option<PathBuf>,
+    ) -> SvLogResult<Self> {
+        let connection = connect(&target, ca_file.as_deref()).context(ForwardSnafu {
+            target: target.to_string(),
+        })?;
+        Ok(SyslogForwarder {
+            target,
+            format,
+            hostname,
+            tz,
+            ca_file,
+            connection,
+        })
+    }
+}
+
+impl SyslogForwarder {
+    fn reconnect_if_closed(&mut self) -> io::Result<()> {
+        if is_closed(&self.connection) {
+            self.connection = connect(&self.target, self.ca_file.as_deref())?;
+        }
+        Ok(())
+    }
+}
+
+impl Forwarder for SyslogForwarder {
+    fn send(&mut self, log_line: &LogLine) -> SvLogResult<()> {
+        let message = format_syslog(log_line, self.format, &self.hostname, &self.tz);
+        // NOTE: messages in a stream are framed by octet counting or a
+        // trailing newline (RFC 6587), datagrams contain one message
+        let frame = match (self.target.transport, self.format) {
+            (Transport::Udp, _) => message,
+            (_, SyslogFormat::Rfc5424) => format!("{} {}", message.len(), message),
+            (_, SyslogFormat::Rfc3164) => format!("{}\n", message),
+        };
+        // NOTE: writing to a connection closed by the server usually still
+        // succeeds once, which would lose the message
+        self.reconnect_if_closed().context(ForwardSnafu {
+            target: self.target.to_string(),
+        })?;
+        let result = match write(&mut self.connection, frame.as_bytes()) {
+            // NOTE: delivery over UDP isn't guaranteed anyway, so a server
+            // which isn't listening (yet) is no reason to stop
+            Err(e) if self.target.transport == Transport::Udp => match e.kind() {
+                ErrorKind::ConnectionRefused => Ok(()),
+                _ => Err(e),
+            },
+            Err(_) => connect(&self.target, self.ca_file.as_deref()).and_then(|connection| {
+                self.connection = connection;
+                write(&mut self.connection, frame.as_bytes())
+            }),
+            Ok(()) => Ok(()),
+        };
+        result.context(ForwardSnafu {
+            target: self.target.to_string(),
+        })
+    }
+
+    // NOTE: a server which is still down is reported by the next send
+    fn keepalive(&mut self) -> SvLogResult<()> {
+        let _ = self.reconnect_if_closed();
+        Ok(())
+    }
+
+    fn target(&self) -> String {
+        self.target.to_string()
+    }
+}
+
+impl Drop for SyslogForwarder {
+    // Closes TLS connections cleanly so that the server doesn't report an
+    // unexpected end of the stream.
+    fn drop(&mut self) {
+        if let Connection::Tls(stream) = &mut self.connection {
+            let _ = stream.shutdown();
+        }
+    }
+}
+
+fn connect(target: &SyslogTarget, ca_file: Option<&Path>) -> io::Result<Connection> {
+    let address = target.address();
+    match target.transport {
+        Transport::Udp => {
+            let bind_address = if target.host.contains(':') {
+                "[::]:0"
+            } else {
+                "0.0.0.0:0"
+            };
+            let socket = UdpSocket::bind(bind_address)?;
+            socket.connect(&address)?;
+            Ok(Connection::Udp(socket))
+        }
+        Transport::Tcp => Ok(Connection::Tcp(TcpStream::connect(&address)?)),
+        Transport::Tls => {
+            let connector = tls_connector(ca_file).map_err(tls_error)?;
+            let stream = TcpStream::connect(&address)?;
+            let stream = connector.connect(&target.host, stream).map_err(tls_error)?;
+            Ok(Connection::Tls(Box::new(stream)))
+        }
+    }
+}
+
+fn write(connection: &mut Connection, frame: &[u8]) -> io::Result<()> {
+    match connection {
+        Connection::Udp(socket) => socket.send(frame).map(|_| ()),
+        Connection::Tcp(stream) => stream.write_all(frame),
+        Connection::Tls(stream) => stream.write_all(frame),
+    }
+}
+
+// Returns whether the server closed the stream, i.e. whether its end can be
+// peeked without blocking. TLS streams aren't read, so data sent by the server
+// (e.g. session tickets) hides the end of the stream. Such connections are
+// only opened again after writing failed.
+fn is_closed(connection: &Connection) -> bool {
+    let stream = match connection {
+        Connection::Udp(_) => return false,
+        Connection::Tcp(stream) => stream,
+        Connection::Tls(stream) => stream.get_ref(),
+    };
+    let mut buf = [0; 1];
+    let closed = match stream
+        .set_nonblocking(true)
+        .and_then(|_| stream.peek(&mut buf))
+    {
+        Ok(length) => length == 0,
+        Err(e) => e.kind() != ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_err() || closed
+}
+
+fn tls_error<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(ErrorKind::Other, format!("TLS: {}", e))
+}
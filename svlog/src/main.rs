@@ -1,46 +1,299 @@
+mod actions;
+mod check;
+mod export;
+mod filter_settings;
+mod forward;
 mod printer;
+mod serve;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
-use svlog_cli::Args;
-use svlog_util::{
-    services::{check_services, file_paths, list_services},
-    SvLogResult,
+use snafu::ensure;
+use svlog_cli::{Args, Command, LogDir};
+use svlog_core::{
+    service_infos, ArchiveSource, Cursor, CursorSource, DirSource, FileSource, LabeledSource,
+    LogSource, Manifest, ServiceInfo, StdinSource,
 };
+use svlog_util::{boot_table, host_name, InvalidArgCombinationSnafu, SvLogResult};
 
-use crate::printer::{LogFile, LogFilterSettings, LogPrinter};
+use crate::{
+    actions::Actions,
+    check::{check, CheckState},
+    export::export,
+    filter_settings::log_filter_settings,
+    forward::{Forwarder, LokiForwarder, SyslogForwarder},
+    printer::{display_tz, print_permission_hint, print_services, LogPrinter, PrintSettings},
+    serve::serve,
+};
+
+// Exit status if entries matched the filters
+static EXIT_MATCH: i32 = 0;
+// Exit status if no entry matched the filters (like grep)
+static EXIT_NO_MATCH: i32 = 1;
+// Exit status if an error occurred
+static EXIT_ERROR: i32 = 2;
 
 fn main() {
     let args = Args::parse();
-    if let Err(e) = try_main(&args) {
-        eprintln!("{}", e);
-        std::process::exit(1)
+    match try_main(&args) {
+        Ok(status) if status == EXIT_MATCH => {}
+        Ok(status) => std::process::exit(status),
+        Err(e) if matches!(args.command, Some(Command::Check { .. })) => {
+            println!("SVLOG {} - {}", CheckState::Unknown.name(), e);
+            std::process::exit(CheckState::Unknown as i32)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            if e.is_permission_denied() {
+                print_permission_hint();
+            }
+            std::process::exit(EXIT_ERROR)
+        }
     }
 }
 
-fn try_main(args: &Args) -> SvLogResult<()> {
+// Returns the exit status, see EXIT_MATCH and EXIT_NO_MATCH. Listing services
+// or boots and exporting always count as match, checks return the state.
+fn try_main(args: &Args) -> SvLogResult<i32> {
+    let use_pager = !args.no_pager && !args.follow && !args.quiet;
     if args.list {
-        list_services(&args.log_dir);
-        std::process::exit(0);
+        ensure!(
+            args.log_dirs
+                .iter()
+                .all(|log_dir| !ArchiveSource::is_archive(Path::new(&log_dir.path))),
+            InvalidArgCombinationSnafu {
+                message: "--list can't be used with archives".to_string(),
+            }
+        );
+        let services: Vec<(&LogDir, Vec<ServiceInfo>)> = args
+            .log_dirs
+            .iter()
+            .map(|log_dir| Ok((log_dir, service_infos(&log_dir.path)?)))
+            .collect::<SvLogResult<_>>()?;
+        let tz = display_tz(args, None);
+        print_services(&services, args.show_labels(), use_pager, &tz, args.output)?;
+        return Ok(EXIT_MATCH);
+    }
+    let (sources, manifest) = log_sources(args)?;
+    let (sources, cursor) = match &args.cursor_file {
+        Some(path) => {
+            let (sources, cursor) = with_cursor(sources, &Cursor::load(path)?);
+            (sources, Some((path.clone(), cursor)))
+        }
+        None => (sources, None),
+    };
+    let print_settings = PrintSettings::from_args(args, manifest.as_ref());
+    let log_filter_settings = log_filter_settings(args, &print_settings.tz, manifest.as_ref())?;
+    let forwarder: Option<Box<dyn Forwarder>> = match &args.command {
+        Some(Command::Export { output }) => {
+            ensure!(
+                !args.follow && !args.list_boots,
+                InvalidArgCombinationSnafu {
+                    message: "export can't be used with --follow or --list-boots".to_string(),
+                }
+            );
+            export(
+                args,
+                &sources,
+                &log_filter_settings,
+                manifest.as_ref(),
+                output,
+            )?;
+            save_cursor(&cursor)?;
+            return Ok(EXIT_MATCH);
+        }
+        Some(Command::Check { warning, critical }) => {
+            ensure!(
+                !args.follow && !args.list_boots,
+                InvalidArgCombinationSnafu {
+                    message: "check can't be used with --follow or --list-boots".to_string(),
+                }
+            );
+            let state = check(
+                &sources,
+                &log_filter_settings,
+                args.show_labels(),
+                *warning,
+                *critical,
+            )?;
+            save_cursor(&cursor)?;
+            return Ok(state as i32);
+        }
+        Some(Command::Forward {
+            target,
+            format,
+            hostname,
+            ca_file,
+        }) => {
+            ensure!(
+                !args.list_boots,
+                InvalidArgCombinationSnafu {
+                    message: "forward can't be used with --list-boots".to_string(),
+                }
+            );
+            Some(Box::new(SyslogForwarder::connect(
+                target.clone(),
+                *format,
+                forward_hostname(hostname, manifest.as_ref()),
+                print_settings.tz,
+                ca_file.clone(),
+            )?))
+        }
+        Some(Command::Loki {
+            url,
+            batch_size,
+            retries,
+            tenant,
+            hostname,
+            ca_file,
+        }) => {
+            ensure!(
+                !args.list_boots,
+                InvalidArgCombinationSnafu {
+                    message: "loki can't be used with --list-boots".to_string(),
+                }
+            );
+            Some(Box::new(LokiForwarder::new(
+                url.clone(),
+                forward_hostname(hostname, manifest.as_ref()),
+                tenant.clone(),
+                *batch_size,
+                *retries,
+                args.follow,
+                ca_file.as_deref(),
+            )?))
+        }
+        Some(Command::Serve {
+            listen,
+            max_streams,
+        }) => {
+            ensure!(
+                !args.follow && !args.list_boots,
+                InvalidArgCombinationSnafu {
+                    message: "serve can't be used with --follow or --list-boots".to_string(),
+                }
+            );
+            serve(args, *listen, *max_streams)?;
+            return Ok(EXIT_MATCH);
+        }
+        None => None,
+    };
+    let actions = if args.follow {
+        Actions::from_args(args)?
+    } else {
+        Actions::default()
+    };
+    let forwarded_to = forwarder.as_ref().map(|forwarder| forwarder.target());
+    let mut printer = LogPrinter::new(sources, &log_filter_settings, &print_settings)
+        .with_actions(actions)
+        .with_forwarder(forwarder)
+        .with_cursor(cursor);
+    if args.list_boots {
+        let boots = match &manifest {
+            Some(manifest) => manifest.boots.clone(),
+            None => boot_table(args.boot_source, Path::new(&args.wtmp), args.log_dir())?,
+        };
+        printer.print_boots(args.jobs, use_pager, &boots)?;
+        return Ok(EXIT_MATCH);
+    }
+    let matches = printer.print_logs(args.jobs, use_pager, args.lines)?;
+    if let Some(target) = &forwarded_to {
+        if !args.follow {
+            println!("Forwarded {} entries to {}", matches, target);
+        }
     }
-    check_services(&args.log_dir, &args.services)?;
-
-    let service_file_paths: Vec<PathBuf> = file_paths(&args.log_dir, &args.services);
-    let log_files = service_log_files(&service_file_paths);
-    let log_filter_settings = LogFilterSettings::from_args(args)?;
-    let mut printer = LogPrinter::new(&args.log_dir, log_files, &log_filter_settings);
-    let use_pager = !args.no_pager && !args.follow;
-    printer.print_logs(args.jobs, use_pager, args.lines)?;
     if args.follow {
         printer.watch_logs()?;
     }
-    Ok(())
+    if matches > 0 || args.follow {
+        Ok(EXIT_MATCH)
+    } else {
+        Ok(EXIT_NO_MATCH)
+    }
+}
+
+type LogSources = Vec<Box<dyn LogSource>>;
+
+// Returns the hostname in the forwarded entries: the given one, the one of
+// the bundle or the one of this machine.
+fn forward_hostname(hostname: &Option<String>, manifest: Option<&Manifest>) -> String {
+    hostname
+        .clone()
+        .or_else(|| manifest.and_then(|m| m.hostname.clone()))
+        .or_else(host_name)
+        .unwrap_or_default()
+}
+
+// Wraps the sources to read only the entries added since the positions of the
+// cursor and returns the positions after reading them.
+fn with_cursor(sources: LogSources, cursor: &Cursor) -> (LogSources, Cursor) {
+    let mut next_cursor = Cursor::default();
+    let sources = sources
+        .into_iter()
+        .map(|source| {
+            let source = CursorSource::new(source, cursor);
+            next_cursor.files.extend(source.cursor().files.clone());
+            Box::new(source) as Box<dyn LogSource>
+        })
+        .collect();
+    (sources, next_cursor)
+}
+
+fn save_cursor(cursor: &Option<(PathBuf, Cursor)>) -> SvLogResult<()> {
+    match cursor {
+        Some((path, cursor)) => cursor.save(path),
+        None => Ok(()),
+    }
+}
+
+static STDIN_FILE: &str = "-";
+static STDIN_SERVICE: &str = "stdin";
+
+// Returns a source for each log directory (or the files given with --file) and
+// the manifest of the first one if it is a bundle.
+fn log_sources(args: &Args) -> SvLogResult<(LogSources, Option<Manifest>)> {
+    if !args.files.is_empty() {
+        return Ok((file_sources(&args.files), None));
+    }
+    let mut sources: LogSources = Vec::new();
+    let mut manifest = None;
+    for (i, log_dir) in args.log_dirs.iter().enumerate() {
+        let source: Box<dyn LogSource> = if ArchiveSource::is_archive(Path::new(&log_dir.path)) {
+            let archive = ArchiveSource::new(PathBuf::from(&log_dir.path))
+                .services(&args.services, &args.exclude_service);
+            if i == 0 {
+                manifest = archive.manifest()?;
+            }
+            Box::new(archive)
+        } else {
+            Box::new(DirSource::new(
+                &log_dir.path,
+                &args.services,
+                &args.exclude_service,
+            )?)
+        };
+        if args.show_labels() {
+            sources.push(Box::new(LabeledSource::new(log_dir.name(), source)));
+        } else {
+            sources.push(source);
+        }
+    }
+    Ok((sources, manifest))
 }
 
-pub fn service_log_files(service_file_paths: &[PathBuf]) -> Vec<LogFile> {
-    service_file_paths
+// Returns the sources of the files given with --file. The paths are made
+// absolute so that their directories can be watched with --follow.
+fn file_sources(files: &[String]) -> LogSources {
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let paths: Vec<PathBuf> = files
         .iter()
-        .map(|path| LogFile::new(path.to_str().unwrap()))
-        .collect()
+        .filter(|file| *file != STDIN_FILE)
+        .map(|file| current_dir.join(file))
+        .collect();
+    let mut sources: LogSources = vec![Box::new(FileSource::new(paths))];
+    if files.iter().any(|file| file == STDIN_FILE) {
+        sources.push(Box::new(StdinSource::new(STDIN_SERVICE)));
+    }
+    sources
 }
@@ -1,127 +1,307 @@
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeSet, HashSet},
     fs::File,
     io::{BufRead, BufReader, Seek, SeekFrom},
-    path::Path,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
 };
 
-use calm_io::{pipefail, stdoutln};
+use chrono::Utc;
 use notify::{
     event::{EventKind, ModifyKind},
     Config, RecommendedWatcher, RecursiveMode, Watcher,
 };
-use pager::Pager;
-use rayon::prelude::{
-    IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
-};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use serde_json::json;
 use snafu::ResultExt;
 use svlog_util::{
-    Cache, OpenFileSnafu, PrintLinesSnafu, SvLogResult, WatchFilesNotifySnafu, WatchFilesSnafu,
+    Boot, Cache, OutputFormat, PrintLinesSnafu, SvLogResult, TimeWindow, WatchFilesNotifySnafu,
+    WatchFilesSnafu,
+};
+
+use svlog_core::{read_log_lines, Cursor, LogFile, LogFilterSettings, LogLine, LogSource};
+
+use crate::{
+    actions::Actions,
+    forward::Forwarder,
+    printer::{
+        output::{
+            format_duration, format_time, format_time_iso, json_entry, print_lines, print_skipped,
+        },
+        PrintSettings,
+    },
 };
 
-use crate::printer::{log_file::*, log_line::*, LogFilterSettings};
+// Time without changes of the files after which the keepalive of the
+// forwarder is called while following the logs
+static KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// Time after which finished commands are reaped and queued commands are
+// started while commands are running
+static ACTIONS_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct LogPrinter<'a> {
-    log_dir: &'a str,
-    log_files: Vec<LogFile<'a>>,
+    sources: Vec<Box<dyn LogSource>>,
+    // Files of the sources which are followed
+    log_files: Vec<LogFile>,
     cache: Cache<String>,
     log_settings: &'a LogFilterSettings,
+    print_settings: &'a PrintSettings,
+    // Commands run for new lines in follow mode
+    actions: Actions,
+    // Server or client the lines are sent to instead of printing them
+    forwarder: Option<Box<dyn Forwarder>>,
+    // File the positions in the log files are stored in and the positions
+    cursor: Option<(PathBuf, Cursor)>,
 }
 
 impl<'a> LogPrinter<'a> {
     pub fn new(
-        log_dir: &'a str,
-        log_files: Vec<LogFile<'a>>,
+        sources: Vec<Box<dyn LogSource>>,
         log_settings: &'a LogFilterSettings,
+        print_settings: &'a PrintSettings,
     ) -> LogPrinter<'a> {
         let cache: Cache<String> = Cache::new(20);
+        // NOTE: the paths are made absolute as the watcher reports absolute
+        // paths, e.g. for "-d ./socklog"
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        let log_files = sources
+            .iter()
+            .flat_map(|source| {
+                let label = source.label();
+                source
+                    .files()
+                    .into_iter()
+                    .map(|path| LogFile::new(current_dir.join(path), label.clone()))
+                    .collect::<Vec<LogFile>>()
+            })
+            .collect();
         LogPrinter {
-            log_dir,
+            sources,
             log_files,
             cache,
             log_settings,
+            print_settings,
+            actions: Actions::default(),
+            forwarder: None,
+            cursor: None,
         }
     }
 
+    // Sets the rules which are applied to the new lines when following the
+    // logs.
+    pub fn with_actions(mut self, actions: Actions) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    // Sets the forwarder the log lines are sent to instead of printing
+    // them.
+    pub fn with_forwarder(mut self, forwarder: Option<Box<dyn Forwarder>>) -> Self {
+        self.forwarder = forwarder;
+        self
+    }
+
+    // Sets the positions after reading the sources, which are stored in the
+    // file after printing the log lines and while following them.
+    pub fn with_cursor(mut self, cursor: Option<(PathBuf, Cursor)>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    // Prints (or forwards) the matching log lines and returns their number.
+    // Nothing is printed in quiet mode.
     pub fn print_logs(
         &mut self,
         jobs: usize,
         use_pager: bool,
         lines: Option<usize>,
-    ) -> SvLogResult<()> {
+    ) -> SvLogResult<usize> {
         if let Some(lines) = lines {
             if lines == 0 {
                 self.jump_to_end();
-                return Ok(());
+                self.save_cursor()?;
+                return Ok(0);
             }
         }
         let log_lines = self.retrieve_log_lines(jobs, lines)?;
-        let formatted_log_lines = log_lines
+        if let Some(forwarder) = &mut self.forwarder {
+            for log_line in &log_lines {
+                forwarder.send(log_line)?;
+            }
+            forwarder.flush()?;
+            self.save_cursor()?;
+            return Ok(log_lines.len());
+        }
+        if self.print_settings.quiet {
+            self.save_cursor()?;
+            return Ok(log_lines.len());
+        }
+        let print_settings = self.print_settings;
+        let formatted_log_lines: Vec<String> = log_lines
             .par_iter()
-            .map(|log_line| log_line.format_with_tz(&self.log_settings.tz))
-            .collect::<Result<BTreeSet<String>, _>>();
-        match formatted_log_lines {
-            Ok(lines) => self
-                .print_lines(use_pager, lines)
-                .context(PrintLinesSnafu {}),
-            Err(e) => Err(e),
+            .map(|log_line| format_log_line(print_settings, log_line))
+            .collect();
+        let mut output: Vec<String> = Vec::with_capacity(formatted_log_lines.len());
+        let mut previous_boot: Option<usize> = None;
+        for (log_line, formatted_log_line) in log_lines.iter().zip(formatted_log_lines) {
+            let boot = self.boot_offset(log_line);
+            if let Some(offset) = boot {
+                if !output.is_empty() && boot != previous_boot {
+                    output.push(self.format_boot_separator(offset));
+                }
+            }
+            previous_boot = boot;
+            output.push(formatted_log_line);
+        }
+        print_lines(use_pager, output).context(PrintLinesSnafu {})?;
+        self.save_cursor()?;
+        Ok(log_lines.len())
+    }
+
+    // Prints a table of the boots (from the first to the current one) with
+    // the number of log lines of each boot.
+    pub fn print_boots(&mut self, jobs: usize, use_pager: bool, boots: &[Boot]) -> SvLogResult<()> {
+        let log_lines = self.retrieve_log_lines(jobs, None)?;
+        let tz = &self.print_settings.tz;
+        let mut rows = vec![format!(
+            "{:>6} {:<25} {:<25} {:>12} {:>8}",
+            "OFFSET", "START", "END", "DURATION", "LINES"
+        )];
+        for (offset, boot) in boots.iter().enumerate().rev() {
+            let boot_window = [TimeWindow::new(Some(boot.start), boot.end)];
+            let lines = log_lines
+                .iter()
+                .filter(|log_line| log_line.is_in(&boot_window))
+                .count();
+            let end = boot
+                .end
+                .map_or_else(|| "still running".to_string(), |end| format_time(&end, tz));
+            let duration = boot.end.unwrap_or_else(|| Utc::now().naive_utc()) - boot.start;
+            rows.push(format!(
+                "{:>6} {:<25} {:<25} {:>12} {:>8}",
+                offset,
+                format_time(&boot.start, tz),
+                end,
+                format_duration(duration),
+                lines
+            ));
         }
+        print_lines(use_pager, rows).context(PrintLinesSnafu {})
     }
 
+    // Follows the files of the sources. Sources without files (e.g. stdin)
+    // are not followed.
     pub fn watch_logs(&mut self) -> SvLogResult<()> {
-        let log_dir_path = Path::new(self.log_dir);
-        let (tx, rx) = std::sync::mpsc::channel();
+        if self.log_files.is_empty() {
+            return Ok(());
+        }
+        let (tx, rx) = mpsc::channel();
         let mut watcher =
             RecommendedWatcher::new(tx, Config::default()).context(WatchFilesNotifySnafu {
                 message: "Failed to create watcher".to_string(),
             })?;
-        watcher
-            .watch(log_dir_path.as_ref(), RecursiveMode::Recursive)
-            .context(WatchFilesNotifySnafu {
-                message: "Failed to create watcher".to_string(),
-            })?;
-        for res in rx {
-            match res {
-                Ok(event) => self.handle_event(&event)?,
-                Err(e) => println!("watch error: {:?}", e),
+        // NOTE: the directories are watched as svlogd replaces "current" when
+        // rotating the logs
+        let dirs: HashSet<&Path> = self
+            .log_files
+            .iter()
+            .filter_map(|log_file| log_file.path.parent())
+            .collect();
+        for dir in dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .context(WatchFilesNotifySnafu {
+                    message: "Failed to create watcher".to_string(),
+                })?;
+        }
+        let mut last_change = Instant::now();
+        loop {
+            let timeout = if self.actions.is_idle() {
+                KEEPALIVE_INTERVAL
+            } else {
+                ACTIONS_INTERVAL
+            };
+            match rx.recv_timeout(timeout) {
+                Ok(Ok(event)) => {
+                    self.handle_event(&event)?;
+                    last_change = Instant::now();
+                }
+                Ok(Err(e)) => eprintln!("watch error: {:?}", e),
+                Err(RecvTimeoutError::Timeout) if last_change.elapsed() >= KEEPALIVE_INTERVAL => {
+                    if let Some(forwarder) = &mut self.forwarder {
+                        forwarder.keepalive()?;
+                    }
+                    last_change = Instant::now();
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
             }
+            self.actions.start_queued();
         }
-        Ok(())
     }
 
+    fn format_boot_separator(&self, offset: usize) -> String {
+        let start = &self.print_settings.boots[offset].start;
+        let tz = &self.print_settings.tz;
+        match self.print_settings.output {
+            OutputFormat::Text => format!("-- Boot {}: {} --", offset, format_time(start, tz)),
+            OutputFormat::Json => json!({
+                "type": "boot",
+                "boot": offset,
+                "start": format_time_iso(start, tz),
+            })
+            .to_string(),
+        }
+    }
+
+    // Returns the offset of the boot the log line belongs to.
+    fn boot_offset(&self, log_line: &LogLine) -> Option<usize> {
+        self.print_settings
+            .boots
+            .iter()
+            .position(|boot| boot.start <= *log_line.date())
+    }
+
+    // Moves the positions of the followed files to the end or, with a
+    // cursor, to the end of what was read.
     fn jump_to_end(&mut self) {
         for log_file in &mut self.log_files {
-            log_file.jump_to_end();
+            match self
+                .cursor
+                .as_ref()
+                .and_then(|(_, cursor)| cursor.get(&log_file.path))
+            {
+                Some(file_cursor) => log_file.position = file_cursor.offset,
+                None => log_file.jump_to_end(),
+            }
         }
     }
 
-    #[pipefail]
-    fn print_lines(&mut self, use_pager: bool, lines: BTreeSet<String>) -> std::io::Result<()> {
-        if use_pager {
-            Pager::new().setup();
-        }
-        for line in lines {
-            stdoutln!("{line}")?;
+    fn save_cursor(&self) -> SvLogResult<()> {
+        match &self.cursor {
+            Some((path, cursor)) => cursor.save(path),
+            None => Ok(()),
         }
-        Ok(())
     }
 
-    fn retrieve_log_lines(
+    // Reads the matching log lines (the last `lines` ones if given) and moves
+    // the followed files to their end.
+    pub fn retrieve_log_lines(
         &mut self,
         jobs: usize,
         lines: Option<usize>,
     ) -> SvLogResult<BTreeSet<LogLine>> {
-        rayon::ThreadPoolBuilder::new()
+        // NOTE: the pool may already exist, e.g. for later queries of the
+        // server
+        let _ = rayon::ThreadPoolBuilder::new()
             .num_threads(jobs)
-            .build_global()
-            .unwrap();
-        let mut log_lines: BTreeSet<LogLine> = self
-            .log_files
-            .par_iter_mut()
-            .map(|f| f.extract_loglines(self.log_settings))
-            .into_par_iter()
-            .flatten()
-            .collect();
+            .build_global();
+        let (mut log_lines, skipped) = read_log_lines(&self.sources, self.log_settings)?;
+        // NOTE: printed before the lines as the pager takes over the terminal
+        print_skipped(&skipped);
+        self.jump_to_end();
         if let Some(n) = lines {
             let skip_amount = if log_lines.len() < n {
                 0
@@ -147,50 +327,123 @@ impl<'a> LogPrinter<'a> {
         Ok(())
     }
 
+    // Prints the new lines of a followed file. Files which can't be read
+    // (e.g. removed while rotating) are skipped until they are modified again.
     fn handle_modified_path(&mut self, path: &Path) -> SvLogResult<()> {
         for i in 0..self.log_files.len() {
-            let path_name = path.to_str().unwrap();
-            if self.log_files[i].name == path_name {
-                let file = File::open(path).context(OpenFileSnafu {
-                    path: format!("{:?}", path),
-                })?;
-                let file_length = file.metadata().unwrap().len();
+            if self.log_files[i].path == path {
+                let (file, metadata) = match File::open(path) {
+                    Ok(file) => match file.metadata() {
+                        Ok(metadata) => (file, metadata),
+                        Err(_) => continue,
+                    },
+                    Err(_) => continue,
+                };
+                let file_length = metadata.len();
                 if file_length < self.log_files[i].position {
                     self.log_files[i].position = 0;
                 }
-                self.process_new_lines(i, &file, file_length)?;
+                self.process_new_lines(i, &file, metadata.ino())?;
             }
         }
         Ok(())
     }
 
+    // Processes the complete lines added to a followed file. A line without
+    // newline at the end is still being written, it is read with the next
+    // change of the file.
     fn process_new_lines(
         &mut self,
         log_file_index: usize,
         file: &File,
-        file_position: u64,
+        inode: u64,
     ) -> SvLogResult<()> {
         let mut reader = BufReader::new(file);
-        let log_file: &mut LogFile = &mut self.log_files[log_file_index];
+        let mut position = self.log_files[log_file_index].position;
         reader
-            .seek(SeekFrom::Start(log_file.position))
+            .seek(SeekFrom::Start(position))
             .context(WatchFilesSnafu {
                 message: "Failed to seek file position.",
             })?;
-        for line in reader.lines().flatten() {
+        let service = self.log_files[log_file_index].service();
+        let label = self.log_files[log_file_index].label.clone();
+        let mut last_date = None;
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(length) if buf.ends_with(b"\n") => position += length as u64,
+                _ => break,
+            }
+            let line = String::from_utf8_lossy(&buf[..buf.len() - 1]).into_owned();
             if self.cache.push(String::from(&line)) {
-                let log_line = LogLine::new(line)?;
-                if log_line.is_match(&self.log_settings.re)
-                    && log_line.has_priority(
-                        &self.log_settings.min_priority,
-                        &self.log_settings.max_priority,
-                    )
-                {
-                    println!("{}", log_line.format_with_tz(&self.log_settings.tz)?);
+                // NOTE: invalid lines are skipped like when reading the files
+                let log_line = match LogLine::new(line) {
+                    Ok(log_line) => log_line
+                        .with_service(service.clone())
+                        .with_label(label.clone()),
+                    Err(_) => continue,
+                };
+                last_date = Some(*log_line.date());
+                if self.log_settings.matches(&log_line) {
+                    let formatted_log_line = format_log_line(self.print_settings, &log_line);
+                    self.output(&log_line, &formatted_log_line)?;
+                    self.actions
+                        .run(&log_line, &self.print_settings.tz, &formatted_log_line);
+                    for (alert_line, command) in self.actions.rate_alerts(&log_line) {
+                        let formatted_alert_line =
+                            format_log_line(self.print_settings, &alert_line);
+                        self.output(&alert_line, &formatted_alert_line)?;
+                        if let Some(command) = command {
+                            self.actions.exec(
+                                &command,
+                                &alert_line,
+                                &self.print_settings.tz,
+                                &formatted_alert_line,
+                            );
+                        }
+                    }
                 }
             }
         }
-        log_file.position = file_position;
+        // NOTE: the cursor is saved only once the lines were sent
+        if let Some(forwarder) = &mut self.forwarder {
+            forwarder.flush()?;
+        }
+        let log_file = &mut self.log_files[log_file_index];
+        log_file.position = position;
+        if let Some((path, cursor)) = &mut self.cursor {
+            cursor.update(&log_file.path, inode, position, last_date);
+            cursor.save(path)?;
+        }
         Ok(())
     }
+
+    // Prints a new log line or sends it to the forwarder.
+    fn output(&mut self, log_line: &LogLine, formatted_log_line: &str) -> SvLogResult<()> {
+        match &mut self.forwarder {
+            Some(forwarder) => forwarder.send(log_line),
+            None => {
+                println!("{}", formatted_log_line);
+                Ok(())
+            }
+        }
+    }
+}
+
+// NOTE: the printer isn't passed to the threads formatting the lines as the
+// forwarder isn't Sync
+fn format_log_line(print_settings: &PrintSettings, log_line: &LogLine) -> String {
+    let tz = &print_settings.tz;
+    let label = log_line.label().filter(|_| print_settings.labels);
+    match (print_settings.output, label) {
+        (OutputFormat::Text, None) => log_line.format_with_tz(tz),
+        (OutputFormat::Text, Some(label)) => format!(
+            "{} {} {}",
+            log_line.format_date(tz),
+            label,
+            log_line.content()
+        ),
+        (OutputFormat::Json, _) => json_entry(log_line, tz, label).to_string(),
+    }
 }
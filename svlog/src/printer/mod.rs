@@ -1,8 +1,9 @@
-mod log_file;
-mod log_filter_settings;
-mod log_line;
 mod log_printer;
+mod output;
+mod print_settings;
+mod service_printer;
 
-pub use log_file::LogFile;
-pub use log_filter_settings::LogFilterSettings;
 pub use log_printer::LogPrinter;
+pub use output::{json_entry, print_permission_hint, print_skipped};
+pub use print_settings::{display_tz, PrintSettings};
+pub use service_printer::{format_services, print_services};
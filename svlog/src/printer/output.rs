@@ -0,0 +1,107 @@
+use calm_io::{pipefail, stdoutln};
+use chrono::{Duration, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use pager::Pager;
+use serde_json::{json, Value};
+use svlog_core::LogLine;
+use svlog_util::SvLogError;
+
+#[pipefail]
+pub fn print_lines(
+    use_pager: bool,
+    lines: impl IntoIterator<Item = String>,
+) -> std::io::Result<()> {
+    if use_pager {
+        Pager::new().setup();
+    }
+    for line in lines {
+        stdoutln!("{line}")?;
+    }
+    Ok(())
+}
+
+// Prints the files and directories which were skipped as they couldn't be
+// read to stderr.
+pub fn print_skipped(skipped: &[SvLogError]) {
+    if skipped.is_empty() {
+        return;
+    }
+    let noun = if skipped.len() == 1 { "path" } else { "paths" };
+    eprintln!("Skipped {} unreadable {}:", skipped.len(), noun);
+    for e in skipped {
+        eprintln!("  {e}");
+    }
+    if skipped.iter().any(SvLogError::is_permission_denied) {
+        print_permission_hint();
+    }
+}
+
+pub fn print_permission_hint() {
+    eprintln!(
+        "Hint: only members of the group owning the logs (e.g. \"socklog\") can read them, \
+        try adding your user to it or run svlog as root"
+    );
+}
+
+// Returns a log line as printed with "--output json".
+pub fn json_entry(log_line: &LogLine, tz: &Option<Tz>, label: Option<&str>) -> Value {
+    let mut entry = json!({
+        "type": "entry",
+        "timestamp": log_line.format_date(tz),
+        "priority": log_line.priority().value(),
+        "content": log_line.content(),
+    });
+    if let Some(label) = label {
+        entry["label"] = json!(label);
+    }
+    entry
+}
+
+pub fn format_time(time: &NaiveDateTime, tz: &Option<Tz>) -> String {
+    match tz {
+        Some(tz) => tz
+            .from_utc_datetime(time)
+            .format("%Y-%m-%d %H:%M:%S%:z")
+            .to_string(),
+        None => time.format("%Y-%m-%d %H:%M:%SZ").to_string(),
+    }
+}
+
+pub fn format_time_iso(time: &NaiveDateTime, tz: &Option<Tz>) -> String {
+    match tz {
+        Some(tz) => tz.from_utc_datetime(time).to_rfc3339(),
+        None => time.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    );
+    if days > 0 {
+        format!("{days}d {time}")
+    } else {
+        time
+    }
+}
+
+// Formats a number of bytes with binary units, e.g. "1.5 MiB".
+pub fn format_size(size: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{size} B")
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
@@ -0,0 +1,54 @@
+use std::{path::Path, str::FromStr};
+
+use chrono_tz::Tz;
+use svlog_cli::Args;
+use svlog_core::Manifest;
+use svlog_util::{boot_table, local_tz, Boot, OutputFormat};
+
+pub struct PrintSettings {
+    // None means UTC
+    pub tz: Option<Tz>,
+    pub output: OutputFormat,
+    // Boots for the separators, empty if they are disabled
+    pub boots: Vec<Boot>,
+    // Whether the labels of the log directories are shown
+    pub labels: bool,
+    // Whether the entries are only counted and not printed
+    pub quiet: bool,
+}
+
+impl PrintSettings {
+    // The boots and timezone are taken from the manifest if a bundle is read.
+    pub fn from_args(args: &Args, manifest: Option<&Manifest>) -> Self {
+        let tz = display_tz(args, manifest);
+        // NOTE: boot separators are optional, so a missing boot table is fine
+        let boots = if args.no_boot_separators {
+            Vec::new()
+        } else if let Some(manifest) = manifest {
+            manifest.boots.clone()
+        } else {
+            boot_table(args.boot_source, Path::new(&args.wtmp), args.log_dir()).unwrap_or_default()
+        };
+        PrintSettings {
+            tz,
+            output: args.output,
+            boots,
+            labels: args.show_labels(),
+            quiet: args.quiet,
+        }
+    }
+}
+
+// Returns the timezone to display times in, None for UTC. Bundles are shown in
+// the timezone of the machine they were exported on.
+pub fn display_tz(args: &Args, manifest: Option<&Manifest>) -> Option<Tz> {
+    if args.utc {
+        None
+    } else if let Some(tz) = args.tz {
+        Some(tz)
+    } else if let Some(tz) = manifest.and_then(|m| Tz::from_str(&m.timezone).ok()) {
+        Some(tz)
+    } else {
+        Some(local_tz())
+    }
+}
@@ -0,0 +1,95 @@
+use chrono_tz::Tz;
+use serde_json::json;
+use snafu::ResultExt;
+use svlog_cli::LogDir;
+use svlog_core::ServiceInfo;
+use svlog_util::{OutputFormat, PrintLinesSnafu, SvLogResult};
+
+use crate::printer::output::{format_size, format_time, format_time_iso, print_lines};
+
+// Prints the services of the log directories with the number and size of their
+// log files, the number of lines in "current", the time of the oldest and
+// newest entry and whether unprocessed files exist. With labels, the services
+// are shown as "<label>/<service>".
+pub fn print_services(
+    log_dirs: &[(&LogDir, Vec<ServiceInfo>)],
+    show_labels: bool,
+    use_pager: bool,
+    tz: &Option<Tz>,
+    output: OutputFormat,
+) -> SvLogResult<()> {
+    let lines = format_services(log_dirs, show_labels, tz, output);
+    print_lines(use_pager, lines).context(PrintLinesSnafu {})
+}
+
+// Returns the lines printed by print_services, a table or one JSON object per
+// service.
+pub fn format_services(
+    log_dirs: &[(&LogDir, Vec<ServiceInfo>)],
+    show_labels: bool,
+    tz: &Option<Tz>,
+    output: OutputFormat,
+) -> Vec<String> {
+    let services: Vec<(String, Option<&str>, &ServiceInfo)> = log_dirs
+        .iter()
+        .flat_map(|(log_dir, services)| {
+            let label = Some(log_dir.name()).filter(|_| show_labels);
+            services.iter().map(move |service| {
+                let name = match label {
+                    Some(label) => format!("{}/{}", label.trim_end_matches('/'), service.name),
+                    None => service.name.clone(),
+                };
+                (name, label, service)
+            })
+        })
+        .collect();
+    match output {
+        OutputFormat::Text => {
+            let width = services
+                .iter()
+                .map(|(name, _, _)| name.len())
+                .max()
+                .unwrap_or(0)
+                .max("SERVICE".len());
+            let mut lines = vec![format!(
+                "   {:<width$} {:>5} {:>10} {:>8} {:<25} {:<25} {}",
+                "SERVICE", "FILES", "SIZE", "CURRENT", "OLDEST", "NEWEST", "UNPROCESSED"
+            )];
+            lines.extend(services.iter().map(|(name, _, service)| {
+                let time = |time: &Option<_>| {
+                    time.as_ref()
+                        .map_or_else(|| "-".to_string(), |time| format_time(time, tz))
+                };
+                format!(
+                    " - {:<width$} {:>5} {:>10} {:>8} {:<25} {:<25} {}",
+                    name,
+                    service.files,
+                    format_size(service.size),
+                    service.current_lines,
+                    time(&service.oldest),
+                    time(&service.newest),
+                    if service.unprocessed { "yes" } else { "no" }
+                )
+            }));
+            lines
+        }
+        OutputFormat::Json => services
+            .iter()
+            .map(|(_, label, service)| {
+                let mut info = json!({
+                    "service": service.name,
+                    "files": service.files,
+                    "size": service.size,
+                    "current_lines": service.current_lines,
+                    "oldest": service.oldest.map(|time| format_time_iso(&time, tz)),
+                    "newest": service.newest.map(|time| format_time_iso(&time, tz)),
+                    "unprocessed": service.unprocessed,
+                });
+                if let Some(label) = label {
+                    info["label"] = json!(label);
+                }
+                info.to_string()
+            })
+            .collect(),
+    }
+}
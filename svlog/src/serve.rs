@@ -0,0 +1,298 @@
+use std::{
+    fmt::Display,
+    io::Write,
+    net::SocketAddr,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use chrono_tz::Tz;
+use clap::Parser;
+use serde_json::{json, Value};
+use svlog_cli::{Args, LogDir};
+use svlog_core::{service_infos, ArchiveSource, LogLine, ServiceInfo};
+use svlog_util::{OutputFormat, ServeSnafu, SvLogError, SvLogResult};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    filter_settings::log_filter_settings,
+    forward::Forwarder,
+    log_sources,
+    printer::{display_tz, format_services, json_entry, LogPrinter, PrintSettings},
+};
+
+// Query parameters of the API and the options of svlog they are passed to
+static QUERY_OPTIONS: [(&str, &str); 7] = [
+    ("priority", "--priority"),
+    ("since", "--since"),
+    ("until", "--until"),
+    ("match", "--match"),
+    ("lines", "--lines"),
+    ("boot_offset", "--boot-offset"),
+    ("exclude_service", "--exclude-service"),
+];
+
+// Query parameters which are passed as flags unless their value is "false"
+static QUERY_FLAGS: [(&str, &str); 2] = [
+    ("boot", "--boot"),
+    ("case_insensitive", "--case-insensitive"),
+];
+
+// Status and message of a failed request
+type ApiError = (u16, String);
+
+// Serves the logs over HTTP until svlog is stopped. Every request is handled
+// in its own thread, so clients following the logs don't block the others.
+// Requests to follow the logs beyond max_streams are rejected.
+pub fn serve(args: &Args, listen: SocketAddr, max_streams: usize) -> SvLogResult<()> {
+    let server = Server::http(listen).map_err(|e| serve_error(&listen, e))?;
+    let address = server.server_addr().to_ip().unwrap_or(listen);
+    println!("Listening on http://{}", address);
+    let streams = Arc::new(AtomicUsize::new(0));
+    for request in server.incoming_requests() {
+        let args = args.clone();
+        let streams = StreamSlots {
+            open: Arc::clone(&streams),
+            max: max_streams,
+        };
+        thread::spawn(move || handle_request(&args, request, &streams));
+    }
+    Ok(())
+}
+
+// Number of the open /api/follow streams and how many may be open
+struct StreamSlots {
+    open: Arc<AtomicUsize>,
+    max: usize,
+}
+
+impl StreamSlots {
+    // Returns a slot which is freed when it is dropped, None if all are taken.
+    fn take(&self) -> Option<StreamSlot> {
+        let slot = StreamSlot(Arc::clone(&self.open));
+        if self.open.fetch_add(1, Ordering::SeqCst) < self.max {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+}
+
+// Slot of a client following the logs
+struct StreamSlot(Arc<AtomicUsize>);
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle_request(args: &Args, request: Request, streams: &StreamSlots) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let result = match (request.method(), path) {
+        (Method::Get, "/api/entries") => {
+            query_args(args, query, false).and_then(|args| entries(&args).map_err(bad_request))
+        }
+        (Method::Get, "/api/services") => services(args).map_err(bad_request),
+        (Method::Get, "/api/follow") => match (query_args(args, query, true), streams.take()) {
+            (Ok(args), Some(_slot)) => return follow(&args, request),
+            (Err(e), _) => Err(e),
+            (_, None) => Err((503, "Too many clients follow the logs".to_string())),
+        },
+        (Method::Get, _) => Err((404, format!("Unknown path \"{}\"", path))),
+        (method, _) => Err((405, format!("Method {} is not allowed", method))),
+    };
+    let response = match result {
+        Ok(body) => json_response(200, body),
+        Err((status, message)) => json_response(status, json!({ "error": message }).to_string()),
+    };
+    // NOTE: the client may be gone already
+    let _ = request.respond(response);
+}
+
+// Returns the arguments of the server with the selection of the entries
+// replaced by the one of the query parameters. The parameters are parsed like
+// the options of svlog, e.g. "priority=..err&since=-1h".
+fn query_args(args: &Args, query: &str, follow: bool) -> Result<Args, ApiError> {
+    let mut argv = vec!["svlog".to_string()];
+    let mut services = Vec::new();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        if key == "service" {
+            services.push(value.into_owned());
+        } else if let Some((_, option)) = QUERY_OPTIONS.iter().find(|(name, _)| *name == key) {
+            argv.push(format!("{}={}", option, value));
+        } else if let Some((_, flag)) = QUERY_FLAGS.iter().find(|(name, _)| *name == key) {
+            if value != "false" {
+                argv.push(flag.to_string());
+            }
+        } else {
+            return Err((400, format!("Unknown parameter \"{}\"", key)));
+        }
+    }
+    if follow {
+        argv.push("--follow".to_string());
+    }
+    // NOTE: services are positional, so they can't be mistaken for options
+    argv.push("--".to_string());
+    argv.extend(services);
+    let query = Args::try_parse_from(argv).map_err(|e| {
+        let message = e.to_string();
+        let message = message.lines().next().unwrap_or_default();
+        (400, message.trim_start_matches("error: ").to_string())
+    })?;
+    Ok(Args {
+        boot: query.boot,
+        boot_offset: query.boot_offset,
+        case_insensitive: query.case_insensitive,
+        exclude_service: query.exclude_service,
+        filter: query.filter,
+        follow: query.follow,
+        lines: query.lines,
+        priority: query.priority,
+        since: query.since,
+        until: query.until,
+        services: query.services,
+        cursor_file: None,
+        list: false,
+        list_boots: false,
+        quiet: false,
+        command: None,
+        ..args.clone()
+    })
+}
+
+// Returns the matching entries as JSON array.
+fn entries(args: &Args) -> SvLogResult<String> {
+    let (sources, manifest) = log_sources(args)?;
+    let print_settings = PrintSettings::from_args(args, manifest.as_ref());
+    let log_filter_settings = log_filter_settings(args, &print_settings.tz, manifest.as_ref())?;
+    let mut printer = LogPrinter::new(sources, &log_filter_settings, &print_settings);
+    let entries: Vec<Value> = printer
+        .retrieve_log_lines(args.jobs, args.lines)?
+        .iter()
+        .map(|log_line| api_entry(log_line, &print_settings.tz, print_settings.labels))
+        .collect();
+    Ok(Value::Array(entries).to_string())
+}
+
+// Returns the services like "--list --output json" as JSON array.
+fn services(args: &Args) -> SvLogResult<String> {
+    // NOTE: the services of archives can't be listed
+    let services: Vec<(&LogDir, Vec<ServiceInfo>)> = args
+        .log_dirs
+        .iter()
+        .filter(|log_dir| !ArchiveSource::is_archive(Path::new(&log_dir.path)))
+        .map(|log_dir| Ok((log_dir, service_infos(&log_dir.path)?)))
+        .collect::<SvLogResult<_>>()?;
+    let tz = display_tz(args, None);
+    let services = format_services(&services, args.show_labels(), &tz, OutputFormat::Json);
+    Ok(format!("[{}]", services.join(",")))
+}
+
+// Sends the last matching entries and then the new ones as Server-Sent
+// Events until the client disconnects. While no entries are sent, a comment is
+// sent from time to time to notice clients which are gone.
+fn follow(args: &Args, request: Request) {
+    let client = request
+        .remote_addr()
+        .map_or_else(|| "client".to_string(), SocketAddr::to_string);
+    let (sources, manifest) = match log_sources(args) {
+        Ok(sources) => sources,
+        Err(e) => return respond_error(request, e),
+    };
+    let print_settings = PrintSettings::from_args(args, manifest.as_ref());
+    let log_filter_settings = match log_filter_settings(args, &print_settings.tz, manifest.as_ref())
+    {
+        Ok(log_filter_settings) => log_filter_settings,
+        Err(e) => return respond_error(request, e),
+    };
+    let mut writer = request.into_writer();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if writer.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    let event_stream = EventStream {
+        writer,
+        client,
+        tz: print_settings.tz,
+        labels: print_settings.labels,
+    };
+    let mut printer = LogPrinter::new(sources, &log_filter_settings, &print_settings)
+        .with_forwarder(Some(Box::new(event_stream)));
+    // NOTE: sending fails once the client disconnected, which ends the stream
+    let _ = printer
+        .print_logs(args.jobs, false, args.lines)
+        .and_then(|_| printer.watch_logs());
+}
+
+// Sends the log lines to a client of /api/follow as Server-Sent Events.
+struct EventStream {
+    writer: Box<dyn Write + Send>,
+    client: String,
+    tz: Option<Tz>,
+    labels: bool,
+}
+
+impl Forwarder for EventStream {
+    fn send(&mut self, log_line: &LogLine) -> SvLogResult<()> {
+        let entry = api_entry(log_line, &self.tz, self.labels);
+        write!(self.writer, "data: {}\n\n", entry)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| serve_error(&self.client, e))
+    }
+
+    // NOTE: writing to a closed connection usually only fails the second
+    // time, so a client which is gone is noticed after two keepalives
+    fn keepalive(&mut self) -> SvLogResult<()> {
+        self.writer
+            .write_all(b":keepalive\n\n")
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| serve_error(&self.client, e))
+    }
+
+    fn target(&self) -> String {
+        self.client.clone()
+    }
+}
+
+// Returns a log line like "--output json" with the service it belongs to.
+fn api_entry(log_line: &LogLine, tz: &Option<Tz>, labels: bool) -> Value {
+    let label = log_line.label().filter(|_| labels);
+    let mut entry = json_entry(log_line, tz, label);
+    entry["service"] = json!(log_line.service());
+    entry
+}
+
+fn json_response(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("header is valid");
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn respond_error(request: Request, e: SvLogError) {
+    let (status, message) = bad_request(e);
+    let body = json!({ "error": message }).to_string();
+    let _ = request.respond(json_response(status, body));
+}
+
+// NOTE: most errors are caused by the query, e.g. an unknown service or an
+// invalid pattern
+fn bad_request(e: SvLogError) -> ApiError {
+    let status = if e.is_permission_denied() { 500 } else { 400 };
+    (status, e.to_string())
+}
+
+fn serve_error(address: &impl Display, e: impl Display) -> SvLogError {
+    ServeSnafu {
+        address: address.to_string(),
+        message: e.to_string(),
+    }
+    .build()
+}
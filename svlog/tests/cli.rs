@@ -1,6 +1,9 @@
 mod test_macros;
 
-use std::process::Command;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
@@ -21,6 +24,31 @@ fn list_services() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn list_services_details() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-l", "--utc");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\\s+SERVICE\\s+FILES\\s+SIZE\\s+CURRENT\\s+OLDEST\\s+NEWEST\\s+UNPROCESSED\n\
+            \\s- daemon\\s+1\\s+340 B\\s+0\\s+2022-09-01 13:32:53Z\\s+2022-09-01 13:39:53Z\\s+yes\n\
+            \\s- kernel\\s+1\\s+477 B\\s+8\\s+2022-09-02 13:32:53Z\\s+2022-09-02 13:39:53Z\\s+no\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn list_services_json() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-l", "--utc", "--output", "json");
+    cmd.success().stdout(contains_all!(
+        r#"{"current_lines":0,"files":1,"newest":"2022-09-01T13:39:53Z","oldest":"2022-09-01T13:32:53Z","service":"daemon","size":340,"unprocessed":true}"#,
+        r#""service":"kernel""#,
+    ));
+    Ok(())
+}
+
 #[test]
 fn no_args() -> Result<(), Box<dyn std::error::Error>> {
     let cmd = svlog!();
@@ -58,6 +86,7 @@ fn priority() -> Result<(), Box<dyn std::error::Error>> {
             .*daemon.err.*\n\
             .*daemon.crit.*\n\
             .*daemon.alert.*\n\
+            -- Boot 1: 2022-09-02 .* --\n\
             .*kern.err.*\n\
             .*kern.crit.*\n\
             .*kern.alert.*\n\
@@ -127,6 +156,1267 @@ fn invalid_service() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn service_pattern() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("k*");
+    cmd.success()
+        .stdout(contains_all!("kern.info").and(predicate::str::contains("daemon").not()));
+    Ok(())
+}
+
+#[test]
+fn service_exclusion() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("^kernel");
+    cmd.success()
+        .stdout(contains_all!("daemon.info").and(predicate::str::contains("kern.").not()));
+    let cmd = svlog!("--exclude-service", "d*");
+    cmd.success()
+        .stdout(contains_all!("kern.info").and(predicate::str::contains("daemon").not()));
+    Ok(())
+}
+
+#[test]
+fn invalid_service_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("k*", "x*", "^y", "--exclude-service", "z");
+    cmd.failure()
+        .stderr(contains_all!("Services \"x*\", \"y\", \"z\" not found"));
+    Ok(())
+}
+
+#[test]
+fn multiple_log_dirs() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = format!("{}/tests/socklog", env!("CARGO_MANIFEST_DIR"));
+    let cmd = svlog!(
+        "-d",
+        format!("a={log_dir}"),
+        "-d",
+        format!("b={log_dir}/"),
+        "-p",
+        "..alert",
+        "--utc",
+        "--no-boot-separators",
+        "kernel"
+    );
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-02T13:38:53.68972Z a kern.alert: .*\n\
+            2022-09-02T13:38:53.68972Z b kern.alert: .*\n\
+            2022-09-02T13:39:53.68972Z a kern.emerg: .*\n\
+            2022-09-02T13:39:53.68972Z b kern.emerg: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    let cmd = svlog!("-d", &log_dir, "-d", format!("b={log_dir}"), "-l");
+    cmd.success()
+        .stdout(contains_all!(format!(" - {log_dir}/kernel"), " - b/kernel"));
+    Ok(())
+}
+
+#[test]
+fn label_json() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = format!("{}/tests/socklog/", env!("CARGO_MANIFEST_DIR"));
+    let cmd = svlog!(
+        "-d",
+        format!("host={log_dir}"),
+        "-p",
+        "emerg",
+        "--output",
+        "json"
+    );
+    cmd.success().stdout(contains_all!(
+        r#""label":"host""#,
+        r#""content":"kern.emerg: "#
+    ));
+    Ok(())
+}
+
+#[test]
+fn export_bundle() -> Result<(), Box<dyn std::error::Error>> {
+    let bundle = std::env::temp_dir().join(format!("svlog_{}.tar.zst", std::process::id()));
+    let cmd = svlog!("-p", "..err", "kernel", "export", "-o", &bundle);
+    cmd.success().stdout(contains_all!("Exported 4 entries"));
+    let cmd = svlog!("-d", &bundle, "--utc", "--list-boots");
+    cmd.success().stdout(contains_all!(
+        "2022-09-01 13:30:00Z",
+        "2022-09-02 13:30:00Z",
+        "still running"
+    ));
+    let cmd = svlog!("-d", &bundle, "--utc", "-o", "1");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-02T13:36:53.68972Z kern.err: it is bad!\n\
+            2022-09-02T13:37:53.68972Z kern.crit: .*\n\
+            2022-09-02T13:38:53.68972Z kern.alert: .*\n\
+            2022-09-02T13:39:53.68972Z kern.emerg: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    let cmd = svlog!("-d", &bundle, "--utc", "-o", "0");
+    cmd.code(1).stdout("");
+    let cmd = svlog!("-d", &bundle, "daemon");
+    cmd.failure()
+        .stderr(contains_all!("Service \"daemon\" not found"));
+    std::fs::remove_file(bundle)?;
+    Ok(())
+}
+
+#[test]
+fn read_file() -> Result<(), Box<dyn std::error::Error>> {
+    let file = format!(
+        "{}/tests/socklog/kernel/current",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let cmd = svlog!("--file", &file, "-p", "..alert", "--tz", "Europe/Zurich");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-02T15:38:53.68972\\+02:00 kern.alert: .*\n\
+            2022-09-02T15:39:53.68972\\+02:00 kern.emerg: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    let cmd = svlog!("--file", &file, "kernel");
+    cmd.failure().stderr(contains_all!("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn read_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let file = format!(
+        "{}/tests/socklog/kernel/current",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let mut cmd = assert_cmd::Command::cargo_bin("svlog")?;
+    cmd.env_remove("TZ")
+        .args([
+            "--no-pager",
+            "--utc",
+            "-p",
+            "emerg",
+            "--file",
+            "-",
+            "--file",
+            &file,
+        ])
+        .write_stdin("2022-09-02T13:39:00.00000 user.emerg: from stdin\nnot a log line\n");
+    cmd.assert().success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-02T13:39:00.00000Z user.emerg: from stdin\n\
+            2022-09-02T13:39:53.68972Z kern.emerg: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+// Creates a log directory with a readable "kernel" service, a "broken"
+// service whose files were removed (dangling symlinks) and a "private" service
+// which is only readable by its owner.
+fn restricted_log_dir(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_{}_{}", name, std::process::id()));
+    for service in ["kernel", "broken", "private"] {
+        std::fs::create_dir_all(log_dir.join(service))?;
+    }
+    std::fs::write(
+        log_dir.join("kernel/current"),
+        "2022-09-02T13:39:53.68972 kern.emerg: readable\n",
+    )?;
+    symlink("/nonexistent", log_dir.join("broken/current"))?;
+    std::fs::write(
+        log_dir.join("private/current"),
+        "2022-09-02T13:39:53.68972 user.emerg: private\n",
+    )?;
+    std::fs::set_permissions(
+        log_dir.join("private"),
+        std::fs::Permissions::from_mode(0o700),
+    )?;
+    std::fs::set_permissions(
+        log_dir.join("private/current"),
+        std::fs::Permissions::from_mode(0o000),
+    )?;
+    Ok(log_dir)
+}
+
+// Whether the permissions of the restricted log directory are enforced, i.e.
+// the tests don't run as root.
+fn permissions_enforced(log_dir: &Path) -> bool {
+    std::fs::File::open(log_dir.join("private/current")).is_err()
+}
+
+#[test]
+fn unreadable_files() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = restricted_log_dir("unreadable")?;
+    let cmd = svlog!("-d", &log_dir, "--utc", "--no-boot-separators");
+    let cmd = cmd
+        .success()
+        .stdout(contains_all!("kern.emerg: readable"))
+        .stderr(contains_all!(
+            "Skipped",
+            "broken/current: No such file or directory"
+        ));
+    if permissions_enforced(&log_dir) {
+        cmd.stderr(contains_all!(
+            "private/current: Permission denied",
+            "\"socklog\""
+        ));
+    }
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn unreadable_log_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = restricted_log_dir("unreadable_dir")?;
+    let cmd = svlog!("-d", log_dir.join("missing"));
+    cmd.failure().stderr(contains_all!(
+        "ReadLogDirError",
+        "No such file or directory"
+    ));
+    if permissions_enforced(&log_dir) {
+        let cmd = svlog!("-d", log_dir.join("private"), "-l");
+        cmd.failure()
+            .stderr(contains_all!("Permission denied", "\"socklog\""));
+    }
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn invalid_regex() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-m", "kern(el");
+    cmd.failure().stderr(contains_all!(
+        "Invalid regular expression \"kern(el\": unclosed group at position 5"
+    ));
+    let cmd = svlog!("-m", "(a{1000}){1000}");
+    cmd.failure()
+        .stderr(contains_all!("exceeds the size limit"));
+    Ok(())
+}
+
+#[test]
+fn exit_status() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-p", "emerg", "kernel");
+    cmd.code(0).stdout(contains_all!("kern.emerg"));
+    let cmd = svlog!("-m", "no such entry");
+    cmd.code(1).stdout("");
+    let cmd = svlog!("nosuchservice");
+    cmd.code(2);
+    Ok(())
+}
+
+#[test]
+fn quiet() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-q", "-p", "..err", "kernel");
+    cmd.code(0).stdout("");
+    let cmd = svlog!(
+        "--quiet",
+        "-p",
+        "..err",
+        "--utc",
+        "-u",
+        "2022-09-01 13:35",
+        "daemon"
+    );
+    cmd.code(1).stdout("");
+    let cmd = svlog!("-q", "-f");
+    cmd.failure().stderr(contains_all!("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn check_thresholds() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-p", "..err", "check", "-w", "3", "-c", "10");
+    cmd.code(1).stdout(
+        "SVLOG WARNING - 8 matching entries (daemon: 4, kernel: 4) | \
+        'daemon'=4;3;10;0 'kernel'=4;3;10;0 total=8;;;0\n",
+    );
+    let cmd = svlog!("-p", "..err", "kernel", "check", "-c", "3");
+    cmd.code(2)
+        .stdout("SVLOG CRITICAL - 4 matching entries (kernel: 4) | 'kernel'=4;;3;0 total=4;;;0\n");
+    let cmd = svlog!("-p", "..err", "check", "-w", "5");
+    cmd.code(0).stdout(contains_all!("SVLOG OK"));
+    let cmd = svlog!("-s", "-5m", "check");
+    cmd.code(0)
+        .stdout("SVLOG OK - no matching entries | total=0;;;0\n");
+    Ok(())
+}
+
+#[test]
+fn check_quoted_service() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = std::env::temp_dir().join(format!("svlog_quote_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("it's"))?;
+    std::fs::write(
+        log_dir.join("it's/current"),
+        "2022-09-02T13:39:53.68972 user.err: quoted\n",
+    )?;
+    let cmd = svlog!("-d", &log_dir, "check");
+    cmd.code(2)
+        .stdout("SVLOG CRITICAL - 1 matching entries (it's: 1) | 'it''s'=1;;0;0 total=1;;;0\n");
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn check_unknown() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("nosuchservice", "check");
+    cmd.code(3).stdout(contains_all!(
+        "SVLOG UNKNOWN - Service \"nosuchservice\" not found"
+    ));
+    Ok(())
+}
+
+#[test]
+fn relative_since() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-s", "-1d");
+    cmd.code(1).stdout("");
+    let cmd = svlog!("-u", "-1d", "-p", "emerg", "--utc", "--no-boot-separators");
+    cmd.code(0)
+        .stdout(contains_all!("daemon.emerg: d0", "kern.emerg"));
+    let cmd = svlog!("-s", "-5x");
+    cmd.failure()
+        .stderr(contains_all!("Could not parse time \"-5x\""));
+    let cmd = svlog!("-s", "-999999999d");
+    cmd.failure()
+        .stderr(contains_all!("Time \"-999999999d\" is out of range"));
+    let cmd = svlog!("-u", "-999999999d");
+    cmd.failure().stderr(contains_all!("is out of range"));
+    Ok(())
+}
+
+#[test]
+fn follow_exec() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_exec_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    let current = log_dir.join("kernel/current");
+    std::fs::write(&current, "2022-09-02T13:39:53.68972 kern.err: old\n")?;
+    let out = log_dir.join("out");
+    let rules = log_dir.join("rules");
+    std::fs::write(
+        &rules,
+        format!(
+            "# critical entries\non-match ^kern\\.crit\nexec echo crit >> {}\n",
+            out.display()
+        ),
+    )?;
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "--utc", "-f", "-d"])
+        .arg(&log_dir)
+        .args(["--debounce", "60", "--on-match", "err", "--exec"])
+        .arg(format!(
+            "cat >> {0}; echo \"$SVLOG_SERVICE $SVLOG_PRIORITY $SVLOG_MESSAGE\" >> {0}",
+            out.display()
+        ))
+        .arg("--rules")
+        .arg(&rules)
+        .stdout(std::process::Stdio::null())
+        .spawn()?;
+    sleep(Duration::from_millis(500));
+    let mut file = OpenOptions::new().append(true).open(&current)?;
+    writeln!(file, "2022-09-02T13:40:00.00000 kern.err: first")?;
+    writeln!(file, "2022-09-02T13:40:01.00000 kern.err: debounced")?;
+    writeln!(file, "2022-09-02T13:40:02.00000 kern.crit: second")?;
+    sleep(Duration::from_millis(500));
+    child.kill()?;
+    child.wait()?;
+    let mut lines: Vec<String> = std::fs::read_to_string(&out)?
+        .lines()
+        .map(String::from)
+        .collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            "2022-09-02T13:40:00.00000Z kern.err: first",
+            "crit",
+            "kernel 3 first",
+        ]
+    );
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn follow_exec_queued() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{
+        fs::OpenOptions,
+        io::{BufRead, BufReader, Write},
+        sync::mpsc,
+        thread::sleep,
+        time::Duration,
+    };
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_queued_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    let current = log_dir.join("kernel/current");
+    std::fs::write(&current, "")?;
+    let out = log_dir.join("out");
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "--utc", "-f", "-d"])
+        .arg(&log_dir)
+        .args(["--max-exec", "1", "--on-match", "slow", "--exec"])
+        .arg(format!(
+            "sleep 1; echo \"$SVLOG_MESSAGE\" >> {}",
+            out.display()
+        ))
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = tx.send(line);
+        }
+    });
+    sleep(Duration::from_millis(500));
+    let mut file = OpenOptions::new().append(true).open(&current)?;
+    file.write_all(b"2022-09-02T13:40:00.00000 slow first\n")?;
+    sleep(Duration::from_millis(200));
+    file.write_all(b"2022-09-02T13:40:01.00000 slow second\n")?;
+    sleep(Duration::from_millis(200));
+    file.write_all(b"2022-09-02T13:40:02.00000 third\n")?;
+    // the new lines are printed while the second command waits for the first
+    let mut printed = Vec::new();
+    while let Ok(line) = rx.recv_timeout(Duration::from_millis(500)) {
+        printed.push(line);
+    }
+    assert_eq!(printed.len(), 3);
+    assert!(printed[2].ends_with("third"));
+    // the queued command is started without new lines
+    sleep(Duration::from_millis(2000));
+    let ran = std::fs::read_to_string(&out).unwrap_or_default();
+    child.kill()?;
+    child.wait()?;
+    assert_eq!(ran, "slow first\nslow second\n");
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn follow_invalid_line() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_invalid_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    let current = log_dir.join("kernel/current");
+    std::fs::write(&current, "2022-09-02T13:39:53.68972 kern.err: old\n")?;
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "--utc", "-f", "-n", "0", "-d"])
+        .arg(&log_dir)
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    sleep(Duration::from_millis(500));
+    OpenOptions::new()
+        .append(true)
+        .open(&current)?
+        .write_all(b"invalid line\n2022-09-02T13:40:00.00000 kern.err: new\n")?;
+    sleep(Duration::from_millis(500));
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "2022-09-02T13:40:00.00000Z kern.err: new\n"
+    );
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn follow_rate_alert() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_rate_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    std::fs::create_dir_all(log_dir.join("daemon"))?;
+    let kernel = log_dir.join("kernel/current");
+    let daemon = log_dir.join("daemon/current");
+    std::fs::write(&kernel, "2022-09-02T13:39:53.68972 kern.err: old\n")?;
+    std::fs::write(&daemon, "2022-09-02T13:39:53.68972 daemon.info: old\n")?;
+    let out = log_dir.join("out");
+    let rules = log_dir.join("rules");
+    std::fs::write(
+        &rules,
+        format!(
+            "rate daemon:3/1h\nexec echo \"$SVLOG_SERVICE $SVLOG_PRIORITY\" >> {}\n",
+            out.display()
+        ),
+    )?;
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "--utc", "-f", "-n", "0", "-d"])
+        .arg(&log_dir)
+        .args(["--rate-alert", "kernel:..err:1/1m", "--rules"])
+        .arg(&rules)
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    sleep(Duration::from_millis(500));
+    // NOTE: each file is written at once to not read partial lines
+    let mut file = OpenOptions::new().append(true).open(&kernel)?;
+    file.write_all(
+        b"2022-09-02T13:40:00.00000 kern.err: first\n\
+        2022-09-02T13:40:01.00000 kern.info: ignored\n\
+        2022-09-02T13:40:02.01230 kern.crit: second\n\
+        2022-09-02T13:40:03.00000 kern.err: third\n",
+    )?;
+    let daemon_lines: String = (0..4)
+        .map(|i| format!("2022-09-02T13:40:0{i}.00000 daemon.info: {i}\n"))
+        .collect();
+    OpenOptions::new()
+        .append(true)
+        .open(&daemon)?
+        .write_all(daemon_lines.as_bytes())?;
+    sleep(Duration::from_millis(500));
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let alerts: Vec<&str> = stdout
+        .lines()
+        .filter(|l| l.contains("svlog.alert"))
+        .collect();
+    assert_eq!(
+        alerts,
+        vec![
+            "2022-09-02T13:40:02.01230Z svlog.alert: rate limit exceeded by \"kernel\": \
+            2 entries with priority 0..3 within 60s (limit 1)",
+            "2022-09-02T13:40:03.00000Z svlog.alert: rate limit exceeded by \"daemon\": \
+            4 entries with priority 0..7 within 3600s (limit 3)",
+        ]
+    );
+    assert_eq!(std::fs::read_to_string(&out)?, "daemon 1\n");
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn invalid_rules() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = std::env::temp_dir().join(format!("svlog_rules_{}", std::process::id()));
+    std::fs::write(&rules, "on-match err\n\nexec\n")?;
+    let cmd = svlog!("-f", "--rules", &rules);
+    cmd.failure().stderr(contains_all!(":3: missing command"));
+    let cmd = svlog!("-f", "--on-match", "err");
+    cmd.failure()
+        .stderr(contains_all!("every --on-match needs an --exec"));
+    std::fs::write(&rules, "rate kernel:20\n")?;
+    let cmd = svlog!("-f", "--rules", &rules);
+    cmd.failure()
+        .stderr(contains_all!(":1: invalid rate rule \"kernel:20\""));
+    let cmd = svlog!("-f", "--rate-alert", "kernel:20/0s");
+    cmd.failure().stderr(contains_all!("kernel:20/0s"));
+    std::fs::remove_file(rules)?;
+    Ok(())
+}
+
+#[test]
+fn forward_udp() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{net::UdpSocket, time::Duration};
+
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let target = format!("udp://{}", socket.local_addr()?);
+    let cmd = svlog!(
+        "-n",
+        "2",
+        "kernel",
+        "forward",
+        &target,
+        "--hostname",
+        "void"
+    );
+    cmd.success()
+        .stdout(contains_all!(format!("Forwarded 2 entries to {target}")));
+    let mut buf = [0; 1024];
+    let mut messages = Vec::new();
+    for _ in 0..2 {
+        let len = socket.recv(&mut buf)?;
+        messages.push(String::from_utf8(buf[..len].to_vec())?);
+    }
+    assert_eq!(
+        messages,
+        vec![
+            "<1>1 2022-09-02T13:38:53.68972Z void kernel - - - *kernel screams*",
+            "<0>1 2022-09-02T13:39:53.68972Z void kernel - - - \
+            the kernel has uninvited you from its birthday party",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn forward_tcp() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{io::Read, net::TcpListener, thread};
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let target = format!("tcp://{}", listener.local_addr()?);
+    let server = thread::spawn(move || -> std::io::Result<String> {
+        let (mut stream, _) = listener.accept()?;
+        let mut received = String::new();
+        stream.read_to_string(&mut received)?;
+        Ok(received)
+    });
+    let cmd = svlog!(
+        "--utc",
+        "-p",
+        "emerg",
+        "kernel",
+        "forward",
+        &target,
+        "--format",
+        "rfc3164",
+        "--hostname",
+        "void"
+    );
+    cmd.success();
+    assert_eq!(
+        server.join().unwrap()?,
+        "<0>Sep  2 13:39:53 void kernel: \
+        the kernel has uninvited you from its birthday party\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn forward_tcp_reconnect() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{
+        fs::OpenOptions,
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread::{self, sleep},
+        time::Duration,
+    };
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_reconnect_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    let current = log_dir.join("kernel/current");
+    std::fs::write(&current, "2022-09-02T13:39:53.68972 kern.err: old\n")?;
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    let target = format!("tcp://{}", address);
+    let server = thread::spawn(move || -> std::io::Result<(String, String)> {
+        // NOTE: the first connection is closed after the first message
+        let (mut stream, _) = listener.accept()?;
+        let mut first = vec![0; 64];
+        let length = stream.read(&mut first)?;
+        first.truncate(length);
+        drop(stream);
+        let (mut stream, _) = listener.accept()?;
+        let mut second = String::new();
+        stream.read_to_string(&mut second)?;
+        Ok((String::from_utf8_lossy(&first).into_owned(), second))
+    });
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--utc", "-f", "-d"])
+        .arg(&log_dir)
+        .args([
+            "forward",
+            &target,
+            "--format",
+            "rfc3164",
+            "--hostname",
+            "void",
+        ])
+        .spawn()?;
+    sleep(Duration::from_millis(500));
+    OpenOptions::new()
+        .append(true)
+        .open(&current)?
+        .write_all(b"2022-09-02T13:40:00.00000 kern.err: new\n")?;
+    sleep(Duration::from_millis(500));
+    child.kill()?;
+    child.wait()?;
+    // NOTE: an empty connection stops the server if svlog didn't reconnect
+    let _ = TcpStream::connect(address);
+    let (first, second) = server.join().unwrap()?;
+    assert_eq!(first, "<3>Sep  2 13:39:53 void kernel: old\n");
+    assert_eq!(second, "<3>Sep  2 13:40:00 void kernel: new\n");
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn forward_tls() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{io::Read, net::TcpListener, thread};
+
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        ec::{EcGroup, EcKey},
+        hash::MessageDigest,
+        nid::Nid,
+        pkey::PKey,
+        ssl::{SslAcceptor, SslMethod},
+        x509::{extension::SubjectAlternativeName, X509NameBuilder, X509},
+    };
+
+    // self-signed certificate for localhost which the client trusts
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", "localhost")?;
+    let name = name.build();
+    let mut cert = X509::builder()?;
+    cert.set_version(2)?;
+    let serial_number = BigNum::from_u32(1)?.to_asn1_integer()?;
+    cert.set_serial_number(&serial_number)?;
+    cert.set_subject_name(&name)?;
+    cert.set_issuer_name(&name)?;
+    cert.set_pubkey(&key)?;
+    let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(1)?);
+    cert.set_not_before(&not_before)?;
+    cert.set_not_after(&not_after)?;
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .build(&cert.x509v3_context(None, None))?;
+    cert.append_extension(san)?;
+    cert.sign(&key, MessageDigest::sha256())?;
+    let cert = cert.build();
+    let ca_file = std::env::temp_dir().join(format!("svlog_ca_{}.pem", std::process::id()));
+    std::fs::write(&ca_file, cert.to_pem()?)?;
+
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
+    acceptor.set_private_key(&key)?;
+    acceptor.set_certificate(&cert)?;
+    let acceptor = acceptor.build();
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let server = thread::spawn(move || -> Vec<u8> {
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream = acceptor.accept(stream).unwrap();
+        let mut received = Vec::new();
+        // NOTE: the data read before an unclean shutdown is kept
+        let _ = stream.read_to_end(&mut received);
+        received
+    });
+    let cmd = svlog!(
+        "-p",
+        "emerg",
+        "kernel",
+        "forward",
+        format!("tls://localhost:{port}"),
+        "--hostname",
+        "void",
+        "--ca-file",
+        &ca_file
+    );
+    cmd.success();
+    let message = "<0>1 2022-09-02T13:39:53.68972Z void kernel - - - \
+            the kernel has uninvited you from its birthday party";
+    assert_eq!(
+        String::from_utf8(server.join().unwrap())?,
+        format!("{} {}", message.len(), message)
+    );
+    std::fs::remove_file(ca_file)?;
+    Ok(())
+}
+
+#[test]
+fn forward_errors() -> Result<(), Box<dyn std::error::Error>> {
+    // NOTE: the port is free again once the listener is dropped
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let target = format!("tcp://127.0.0.1:{port}");
+    let cmd = svlog!("forward", &target);
+    cmd.failure()
+        .stderr(contains_all!(format!("ForwardError: {target}")));
+    let cmd = svlog!("forward", "http://localhost");
+    cmd.failure()
+        .stderr(contains_all!("Invalid target \"http://localhost\""));
+    let cmd = svlog!("forward", "udp://localhost", "--format", "json");
+    cmd.failure()
+        .stderr(contains_all!("Invalid format \"json\""));
+    Ok(())
+}
+
+// Requests (head and body) received by the mock server
+type MockRequests = std::thread::JoinHandle<std::io::Result<Vec<(String, String)>>>;
+
+// Starts an HTTP server which answers the next requests with the given
+// statuses and returns its URL and the received requests.
+fn mock_loki(statuses: Vec<u16>) -> std::io::Result<(String, MockRequests)> {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (stream, _) = listener.accept()?;
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                if reader.read_line(&mut head)? == 0 {
+                    break;
+                }
+            }
+            let length: usize = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length: ")
+                        .map(String::from)
+                })
+                .map_or(0, |length| length.trim().parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            let text = if status == 204 { "" } else { "entry too old" };
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {status} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{text}",
+                text.len()
+            )?;
+            requests.push((head, String::from_utf8_lossy(&body).into_owned()));
+        }
+        Ok(requests)
+    });
+    Ok((url, server))
+}
+
+// Returns the number of entries in the body of a push request.
+fn loki_entries(body: &str) -> usize {
+    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+    body["streams"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stream| stream["values"].as_array().unwrap().len())
+        .sum()
+}
+
+#[test]
+fn loki_push() -> Result<(), Box<dyn std::error::Error>> {
+    let (url, server) = mock_loki(vec![500, 204, 204])?;
+    let cmd = svlog!(
+        "-n",
+        "3",
+        "kernel",
+        "loki",
+        &url,
+        "--batch-size",
+        "2",
+        "--hostname",
+        "void",
+        "--tenant",
+        "ops"
+    );
+    cmd.success()
+        .stdout(format!("Forwarded 3 entries to {url}/loki/api/v1/push\n"))
+        .stderr(contains_all!("status 500", "retrying"));
+    let requests = server.join().unwrap()?;
+    for (head, _) in &requests {
+        assert!(head.starts_with("POST /loki/api/v1/push HTTP/1.1\r\n"));
+        assert!(head.to_lowercase().contains("x-scope-orgid: ops\r\n"));
+    }
+    // the first batch is pushed again
+    assert_eq!(requests[0].1, requests[1].1);
+    let entries: Vec<usize> = requests
+        .iter()
+        .map(|(_, body)| loki_entries(body))
+        .collect();
+    assert_eq!(entries, vec![2, 2, 1]);
+    let body: serde_json::Value = serde_json::from_str(&requests[2].1)?;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "streams": [{
+                "stream": {"facility": "kern", "host": "void", "priority": "emerg", "service": "kernel"},
+                "values": [[
+                    "1662125993689720000",
+                    "the kernel has uninvited you from its birthday party"
+                ]]
+            }]
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn loki_follow() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_loki_{}", std::process::id()));
+    let service = log_dir.join("kernel");
+    std::fs::create_dir_all(&service)?;
+    let current = service.join("current");
+    let cursor = log_dir.join("cursor.json");
+    std::fs::write(&current, "2022-09-02T13:40:00.00000 kern.err: old\n")?;
+    // the push of the old entry fails once, the new entry is pushed in follow
+    // mode
+    let (url, server) = mock_loki(vec![503, 204, 204])?;
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "-f", "-d"])
+        .arg(&log_dir)
+        .arg("--cursor-file")
+        .arg(&cursor)
+        .args(["loki", &url, "--retries", "1"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    sleep(Duration::from_millis(1000));
+    OpenOptions::new()
+        .append(true)
+        .open(&current)?
+        .write_all(b"2022-09-02T13:40:01.00000 kern.err: new\n")?;
+    let requests = server.join().unwrap()?;
+    sleep(Duration::from_millis(200));
+    child.kill()?;
+    child.wait()?;
+    let entries: Vec<usize> = requests
+        .iter()
+        .map(|(_, body)| loki_entries(body))
+        .collect();
+    assert_eq!(entries, vec![1, 1, 1]);
+    assert!(requests[2].1.contains("\"new\""));
+    let saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&cursor)?)?;
+    let offsets: Vec<u64> = saved["files"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|file| file["offset"].as_u64().unwrap())
+        .collect();
+    assert_eq!(offsets, vec![std::fs::metadata(&current)?.len()]);
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn loki_follow_outage() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_outage_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    let current = log_dir.join("kernel/current");
+    std::fs::write(&current, "2022-09-02T13:40:00.00000 kern.err: old\n")?;
+    // the push of the old entry fails beyond the retries, which doesn't stop
+    // following the logs
+    let (url, server) = mock_loki(vec![503, 503, 204, 204])?;
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "-f", "-d"])
+        .arg(&log_dir)
+        .args(["loki", &url, "--retries", "0"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    sleep(Duration::from_millis(2500));
+    OpenOptions::new()
+        .append(true)
+        .open(&current)?
+        .write_all(b"2022-09-02T13:40:01.00000 kern.err: new\n")?;
+    sleep(Duration::from_millis(200));
+    assert!(child.try_wait()?.is_none());
+    let requests = server.join().unwrap()?;
+    child.kill()?;
+    child.wait()?;
+    let contents: Vec<bool> = requests
+        .iter()
+        .map(|(_, body)| body.contains("\"old\""))
+        .collect();
+    assert_eq!(contents, vec![true, true, true, false]);
+    assert!(requests[3].1.contains("\"new\""));
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn loki_errors() -> Result<(), Box<dyn std::error::Error>> {
+    // NOTE: client errors aren't retried
+    let (url, server) = mock_loki(vec![400])?;
+    let cmd = svlog!("kernel", "loki", &url, "--retries", "3");
+    cmd.failure().stderr(contains_all!(format!(
+        "LokiError: {url}/loki/api/v1/push: status 400: entry too old"
+    )));
+    assert_eq!(server.join().unwrap()?.len(), 1);
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let url = format!("http://127.0.0.1:{port}/push");
+    let cmd = svlog!("kernel", "loki", &url, "--retries", "0");
+    cmd.failure()
+        .stderr(contains_all!(format!("LokiError: {url}: ")));
+    let cmd = svlog!("loki", "localhost:3100");
+    cmd.failure()
+        .stderr(contains_all!("Invalid URL \"localhost:3100\""));
+    let cmd = svlog!(
+        "kernel",
+        "loki",
+        "https://localhost:3100",
+        "--ca-file",
+        "/nonexistent/ca.pem"
+    );
+    cmd.failure().stderr(contains_all!(
+        "LokiError: https://localhost:3100/loki/api/v1/push: /nonexistent/ca.pem: "
+    ));
+    Ok(())
+}
+
+#[test]
+fn serve() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{
+        fs::OpenOptions,
+        io::{BufRead, BufReader, Write},
+        net::TcpStream,
+        process::Stdio,
+        thread::sleep,
+        time::Duration,
+    };
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_serve_{}", std::process::id()));
+    std::fs::create_dir_all(log_dir.join("kernel"))?;
+    std::fs::create_dir_all(log_dir.join("daemon"))?;
+    let current = log_dir.join("kernel/current");
+    std::fs::write(
+        &current,
+        "2022-09-02T13:40:00.00000 kern.info: first\n\
+        2022-09-02T13:40:01.00000 kern.err: second\n\
+        2022-09-02T13:40:02.00000 kern.err: third\n",
+    )?;
+    std::fs::write(
+        log_dir.join("daemon/current"),
+        "2022-09-02T13:40:03.00000 daemon.err: fourth\n",
+    )?;
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--utc", "-d"])
+        .arg(&log_dir)
+        .args(["serve", "--listen", "127.0.0.1:0", "--max-streams", "1"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line)?;
+    let url = line.trim().trim_start_matches("Listening on ").to_string();
+    let get = |path: &str| -> Result<(u16, serde_json::Value), Box<dyn std::error::Error>> {
+        let response = match ureq::get(&format!("{url}{path}")).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.into()),
+        };
+        Ok((
+            response.status(),
+            serde_json::from_str(&response.into_string()?)?,
+        ))
+    };
+    let messages = |entries: &serde_json::Value| -> Vec<String> {
+        entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["content"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let (status, entries) = get("/api/entries?priority=..err")?;
+    assert_eq!(status, 200);
+    assert_eq!(
+        messages(&entries),
+        vec!["kern.err: second", "kern.err: third", "daemon.err: fourth"]
+    );
+    assert_eq!(
+        entries[0],
+        serde_json::json!({
+            "type": "entry",
+            "timestamp": "2022-09-02T13:40:01.00000Z",
+            "priority": 3,
+            "content": "kern.err: second",
+            "service": "kernel"
+        })
+    );
+    let (_, entries) = get("/api/entries?service=kernel&lines=1")?;
+    assert_eq!(messages(&entries), vec!["kern.err: third"]);
+    let (_, entries) = get("/api/entries?since=2022-09-02+13%3A40%3A01&until=2022-09-02+13:40:02")?;
+    assert_eq!(
+        messages(&entries),
+        vec!["kern.err: second", "kern.err: third"]
+    );
+    let (_, entries) = get("/api/entries?match=FIRST&case_insensitive=true")?;
+    assert_eq!(messages(&entries), vec!["kern.info: first"]);
+    let (_, services) = get("/api/services")?;
+    let names: Vec<&str> = services
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|service| service["service"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["daemon", "kernel"]);
+
+    let (status, error) = get("/api/entries?priority=loud")?;
+    assert_eq!(status, 400);
+    assert!(error["error"].as_str().unwrap().contains("--priority"));
+    let (status, error) = get("/api/entries?lines=1&since=-1h")?;
+    assert_eq!(status, 400);
+    assert!(error["error"]
+        .as_str()
+        .unwrap()
+        .contains("cannot be used with"));
+    assert_eq!(get("/api/entries?level=err")?.0, 400);
+    assert_eq!(get("/api/entries?service=nope")?.0, 400);
+    assert_eq!(get("/api/nope")?.0, 404);
+
+    // the last entry and then the new ones are streamed
+    let address = url.trim_start_matches("http://");
+    let follow = |query: &str| -> std::io::Result<(String, BufReader<TcpStream>)> {
+        let mut stream = TcpStream::connect(address)?;
+        write!(
+            stream,
+            "GET /api/follow?{query} HTTP/1.1\r\nHost: {address}\r\n\r\n"
+        )?;
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            if reader.read_line(&mut head)? == 0 {
+                break;
+            }
+        }
+        Ok((head, reader))
+    };
+    let (head, mut reader) = follow("service=kernel&lines=1")?;
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(head.contains("Content-Type: text/event-stream\r\n"));
+    let mut next_event = || -> std::io::Result<serde_json::Value> {
+        let mut event = String::new();
+        reader.read_line(&mut event)?;
+        reader.read_line(&mut String::new())?;
+        Ok(serde_json::from_str(event.trim_start_matches("data: ")).unwrap())
+    };
+    assert_eq!(next_event()?["content"], "kern.err: third");
+    sleep(Duration::from_millis(500));
+    OpenOptions::new()
+        .append(true)
+        .open(&current)?
+        .write_all(b"2022-09-02T13:40:04.00000 kern.warn: fifth\n")?;
+    let event = next_event()?;
+    assert_eq!(event["content"], "kern.warn: fifth");
+    assert_eq!(event["timestamp"], "2022-09-02T13:40:04.00000Z");
+
+    // the stream of a client which disconnected ends with the next entries
+    assert!(follow("")?.0.starts_with("HTTP/1.1 503 "));
+    drop(reader);
+    for i in 5..7 {
+        sleep(Duration::from_millis(300));
+        OpenOptions::new()
+            .append(true)
+            .open(&current)?
+            .write_all(format!("2022-09-02T13:40:0{i}.00000 kern.info: {i}\n").as_bytes())?;
+    }
+    sleep(Duration::from_millis(300));
+    assert!(follow("")?.0.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    child.kill()?;
+    child.wait()?;
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
+#[test]
+fn cursor_file() -> Result<(), Box<dyn std::error::Error>> {
+    use std::{fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+
+    let log_dir = std::env::temp_dir().join(format!("svlog_cursor_{}", std::process::id()));
+    let service = log_dir.join("kernel");
+    std::fs::create_dir_all(&service)?;
+    let current = service.join("current");
+    let cursor = log_dir.join("cursor.json");
+    std::fs::write(
+        &current,
+        "2022-09-02T13:40:00.00000 kern.err: first\n2022-09-02T13:40:01.00000 kern.err: second\n",
+    )?;
+    let append = |line: &str| -> std::io::Result<()> {
+        writeln!(OpenOptions::new().append(true).open(&current)?, "{line}")
+    };
+    macro_rules! svlog_cursor {
+        () => {
+            svlog!("--utc", "-d", &log_dir, "--cursor-file", &cursor)
+        };
+    }
+    svlog_cursor!().success().stdout(
+        "2022-09-02T13:40:00.00000Z kern.err: first\n2022-09-02T13:40:01.00000Z kern.err: second\n",
+    );
+    svlog_cursor!().code(1).stdout("");
+    append("2022-09-02T13:40:02.00000 kern.err: third")?;
+    svlog_cursor!()
+        .success()
+        .stdout("2022-09-02T13:40:02.00000Z kern.err: third\n");
+
+    // svlogd rotates "current"
+    append("2022-09-02T13:40:03.00000 kern.err: fourth")?;
+    std::fs::rename(&current, service.join("@400000006312087b00000000.s"))?;
+    std::fs::write(&current, "2022-09-02T13:40:04.00000 kern.err: fifth\n")?;
+    svlog_cursor!().success().stdout(
+        "2022-09-02T13:40:03.00000Z kern.err: fourth\n2022-09-02T13:40:04.00000Z kern.err: fifth\n",
+    );
+
+    // follow mode stores the positions of the new entries
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("svlog"))
+        .env(
+            "SVLOG_WTMP",
+            format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")),
+        )
+        .args(["--no-pager", "--utc", "-f", "-d"])
+        .arg(&log_dir)
+        .arg("--cursor-file")
+        .arg(&cursor)
+        .stdout(std::process::Stdio::null())
+        .spawn()?;
+    sleep(Duration::from_millis(500));
+    append("2022-09-02T13:40:05.00000 kern.err: sixth")?;
+    sleep(Duration::from_millis(500));
+    child.kill()?;
+    child.wait()?;
+    append("2022-09-02T13:40:06.00000 kern.err: seventh")?;
+    svlog_cursor!()
+        .success()
+        .stdout("2022-09-02T13:40:06.00000Z kern.err: seventh\n");
+
+    std::fs::write(&cursor, "not json")?;
+    svlog_cursor!()
+        .failure()
+        .stderr(contains_all!("CursorError", "cursor.json"));
+    std::fs::remove_dir_all(log_dir)?;
+    Ok(())
+}
+
 #[test]
 fn invalid_args_1() -> Result<(), Box<dyn std::error::Error>> {
     let cmd = svlog!("--boot-offset", "1", "--follow");
@@ -151,9 +1441,9 @@ fn invalid_args_2() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn invalid_args_3() -> Result<(), Box<dyn std::error::Error>> {
-    let cmd = svlog!("--lines", "1", "--boot");
+    let cmd = svlog!("--boot-offset", "3..1");
     cmd.failure()
-        .stderr(contains_all!("--lines", "cannot be used with", "--boot",));
+        .stderr(contains_all!("Invalid boot offset \"3..1\""));
     Ok(())
 }
 
@@ -172,3 +1462,272 @@ fn invalid_args_5() -> Result<(), Box<dyn std::error::Error>> {
         .stderr(contains_all!("--lines", "cannot be used with", "--until",));
     Ok(())
 }
+
+#[test]
+fn timezone() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("--tz", "America/New_York", "--lines", "2", "kernel");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-02T09:38:53.68972-04:00 kern.alert.*\n\
+            2022-09-02T09:39:53.68972-04:00 kern.emerg.*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn timezone_since() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("--tz", "Europe/Zurich", "-s", "2022-09-02 15:38", "kernel");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-02T15:38:53.68972\\+02:00.*\n\
+            2022-09-02T15:39:53.68972\\+02:00.*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn timezone_env() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("svlog")?;
+    cmd.env(
+        "SOCKLOG_LOG_DIR",
+        format!("{}/tests/socklog/", env!("CARGO_MANIFEST_DIR")),
+    )
+    .env("TZ", ":Asia/Tokyo")
+    .args(["--no-pager", "--lines", "1", "kernel"]);
+    cmd.assert()
+        .success()
+        .stdout(contains_all!("2022-09-02T22:39:53.68972+09:00 kern.emerg"));
+    Ok(())
+}
+
+#[test]
+fn invalid_timezone() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("--tz", "Foo/Bar");
+    cmd.failure()
+        .stderr(contains_all!("Invalid timezone \"Foo/Bar\""));
+    Ok(())
+}
+
+#[test]
+fn dst_policy_error() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!(
+        "--tz",
+        "Europe/Zurich",
+        "--dst-policy",
+        "error",
+        "-s",
+        "2022-03-27 02:30"
+    );
+    cmd.failure().stderr(contains_all!(
+        "2022-03-27 02:30:00",
+        "does not exist",
+        "Europe/Zurich"
+    ));
+    Ok(())
+}
+
+#[test]
+fn dst_policy_gap() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("--tz", "Europe/Zurich", "-s", "2022-03-27 02:30", "kernel");
+    cmd.success().stdout(contains_all!("kern.emerg"));
+    Ok(())
+}
+
+#[test]
+fn boot_offset() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-o", "1", "--utc");
+    cmd.success()
+        .stdout(predicate::str::is_match("^(2022-09-02T.* kern\\..*\n){8}$").unwrap());
+    Ok(())
+}
+
+#[test]
+fn boot_offset_with_shutdown() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-o", "2", "--utc");
+    cmd.success()
+        .stdout(predicate::str::is_match("^(2022-09-01T.* d(ae|ea)mon\\..*\n){8}$").unwrap());
+    Ok(())
+}
+
+#[test]
+fn boot_offset_not_found() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-o", "3");
+    cmd.failure()
+        .stderr(contains_all!("couldn't find boot with offset 3"));
+    let cmd = svlog!("-o", "1..99999999999");
+    cmd.failure()
+        .stderr(contains_all!("couldn't find boot with offset 3"));
+    Ok(())
+}
+
+#[test]
+fn boot_source_logs() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = format!("{}/tests/socklog_boots/", env!("CARGO_MANIFEST_DIR"));
+    let cmd = svlog!("-d", log_dir, "--boot-source", "logs", "-o", "1", "--utc");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-01T13:30:02.00000Z kern.info.*\n\
+            2022-09-01T13:30:02.00000Z kern.notice.*\n\
+            2022-09-01T13:31:00.00000Z daemon.info.*\n\
+            2022-09-01T13:35:00.00000Z kern.warn.*\n\
+            2022-09-01T13:36:00.00000Z daemon.notice.*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn boot_source_logs_current() -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = format!("{}/tests/socklog_boots/", env!("CARGO_MANIFEST_DIR"));
+    let cmd = svlog!("-d", log_dir, "--boot-source", "logs", "-b", "--utc");
+    cmd.success()
+        .stdout(predicate::str::is_match("^(2022-09-02T.*\n){4}$").unwrap());
+    Ok(())
+}
+
+#[test]
+fn list_boots() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("--list-boots", "--tz", "Europe/Zurich");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            OFFSET +START +END +DURATION +LINES\n\
+            \\s+2 2022-09-01 15:30:00\\+02:00 2022-09-01 15:40:00\\+02:00 +00:10:00 +8\n\
+            \\s+1 2022-09-02 15:30:00\\+02:00 2022-09-03 09:59:59\\+02:00 +18:29:59 +8\n\
+            \\s+0 2022-09-03 10:00:00\\+02:00 still running +.* +0\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn list_boots_filtered() -> Result<(), Box<dyn std::error::Error>> {
+    let wtmp = format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR"));
+    let cmd = svlog!(
+        "--wtmp",
+        wtmp,
+        "--list-boots",
+        "--utc",
+        "-p",
+        "..err",
+        "kernel"
+    );
+    cmd.success().stdout(contains_all!(
+        "     2 2022-09-01 13:30:00Z      2022-09-01 13:40:00Z          00:10:00        0",
+        "     1 2022-09-02 13:30:00Z      2022-09-03 07:59:59Z          18:29:59        4",
+    ));
+    Ok(())
+}
+
+#[test]
+fn boot_separators() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-p", "emerg", "--utc");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-01T13:39:53.68972Z daemon.emerg: d0\n\
+            -- Boot 1: 2022-09-02 13:30:00Z --\n\
+            2022-09-02T13:39:53.68972Z kern.emerg: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn no_boot_separators() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-p", "emerg", "--no-boot-separators");
+    cmd.success()
+        .stdout(predicate::str::is_match("^(.*emerg.*\n){2}$").unwrap());
+    Ok(())
+}
+
+#[test]
+fn output_json() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-p", "emerg", "--output", "json", "--tz", "Europe/Zurich");
+    cmd.success().stdout(contains_all!(
+        "{\"content\":\"daemon.emerg: d0\",\"priority\":0,\"timestamp\":\"2022-09-01T15:39:53.68972+02:00\",\"type\":\"entry\"}\n\
+        {\"boot\":1,\"start\":\"2022-09-02T15:30:00+02:00\",\"type\":\"boot\"}\n\
+        {\"content\":\"kern.emerg: "
+    ));
+    Ok(())
+}
+
+#[test]
+fn boot_offset_range() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-o", "1..2", "-p", "emerg", "--utc");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-01T13:39:53.68972Z daemon.emerg: d0\n\
+            -- Boot 1: .* --\n\
+            2022-09-02T13:39:53.68972Z kern.emerg: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn boot_offset_list() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-o", "0,2", "-p", "emerg", "--utc");
+    cmd.success().stdout(
+        predicate::str::is_match("^2022-09-01T13:39:53.68972Z daemon.emerg: d0\n$").unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn boot_offset_with_lines() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!("-o", "2", "-n", "2", "--utc");
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-01T13:38:53.68972Z daemon.alert: d1\n\
+            2022-09-01T13:39:53.68972Z daemon.emerg: d0\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
+
+#[test]
+fn boot_offset_with_since() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = svlog!(
+        "-o",
+        "1..2",
+        "-s",
+        "2022-09-01 13:39",
+        "-u",
+        "2022-09-02 13:33",
+        "--utc"
+    );
+    cmd.success().stdout(
+        predicate::str::is_match(
+            "^\
+            2022-09-01T13:39:53.68972Z daemon.emerg: d0\n\
+            -- Boot 1: .* --\n\
+            2022-09-02T13:32:53.68972Z kern.debug: .*\n\
+            2022-09-02T13:33:53.68972Z kern.info: .*\n\
+            $",
+        )
+        .unwrap(),
+    );
+    Ok(())
+}
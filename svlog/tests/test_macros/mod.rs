@@ -4,6 +4,8 @@ macro_rules! svlog {
         {
             let mut cmd = Command::cargo_bin("svlog")?;
             cmd.env("SOCKLOG_LOG_DIR", format!("{}/tests/socklog/", env!("CARGO_MANIFEST_DIR")));
+            cmd.env("SVLOG_WTMP", format!("{}/tests/wtmp", env!("CARGO_MANIFEST_DIR")));
+            cmd.env_remove("TZ");
             cmd.arg("--no-pager");
             $( cmd.arg($arg); )*
             cmd.assert()
@@ -1,17 +1,22 @@
 use std::{
     error::Error,
     fmt,
-    ops::{Add, Sub},
+    net::SocketAddr,
+    ops::{Add, RangeInclusive, Sub},
+    path::PathBuf,
 };
 
 use chrono::{Duration, NaiveDateTime, Utc};
-use clap::{builder::ArgPredicate, Parser};
-use svlog_util::{regex, LogPriority};
+use chrono_tz::Tz;
+use clap::{builder::ArgPredicate, Parser, Subcommand};
+use svlog_util::{
+    regex, BootSource, DstPolicy, LogPriority, OutputFormat, RateRule, SyslogFormat, SyslogTarget,
+};
 
 static HELP_TEMPLATE: &str = "USAGE: {usage}\n{about}\n\n{all-args}";
 static CLI_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Clone, Debug)]
 #[command(author, version, about, long_about = None)]
 #[clap(
     about = "Query socklog log files",
@@ -21,27 +26,130 @@ static CLI_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
     help_template = HELP_TEMPLATE,
     // FIXME: add license to man page properly when its supported
     //   https://github.com/clap-rs/clap/issues/3354
-    after_long_help = "License: MIT",
+    after_long_help = "Exit status: 0 if entries matched, 1 if no entry matched and 2 if an \
+        error occurred.\n\nLicense: MIT",
     version,
+    subcommand_precedence_over_arg = true,
 )]
 pub struct Args {
     /// Only show logs since the last (re)boot.
-    #[clap(short, long, conflicts_with_all = &["boot_offset", "lines", "since", "until"])]
+    #[clap(short, long, conflicts_with = "boot_offset")]
     pub boot: bool,
 
-    /// Directory where the log files are locates
+    /// Where to read the boots of --boot and --boot-offset from: "wtmp" (the
+    /// file given with --wtmp), "logs" (detected from the logs of the kernel
+    /// service) or "both". With "wtmp", the current boot is taken from the
+    /// system. Use "logs" for log files copied from another machine.
     #[clap(
-        short = 'd',
         long,
+        value_name = "SOURCE",
+        value_parser = parse_boot_source,
+        default_value = "wtmp"
+    )]
+    pub boot_source: BootSource,
+
+    /// Directory where the log files are locates or a tar archive of it, e.g.
+    /// a bundle written by "export" (.tar, .tar.gz or .tar.zst). The boots and
+    /// the timezone of a bundle are taken from its manifest. Can be given
+    /// several times to merge the logs of several directories, optionally with
+    /// a LABEL shown with their lines (e.g. "web=/mnt/web/var/log/socklog/").
+    /// Boots are always read from the first directory.
+    #[clap(
+        short = 'd',
+        long = "log-dir",
+        value_name = "[LABEL=]DIR",
+        value_parser = parse_log_dir,
         default_value = "/var/log/socklog/",
         env = "SOCKLOG_LOG_DIR"
     )]
-    pub log_dir: String,
+    pub log_dirs: Vec<LogDir>,
+
+    /// How to interpret times of --since and --until which are ambiguous or
+    /// don't exist due to a daylight saving time transition. "earliest" and
+    /// "latest" pick the earlier or later of two ambiguous points in time,
+    /// "error" rejects such times. Nonexistent times are mapped to the moment
+    /// of the transition unless "error" is used.
+    #[clap(long, value_name = "POLICY", value_parser = parse_dst_policy, default_value = "earliest")]
+    pub dst_policy: DstPolicy,
+
+    /// Exclude the services matching the shell-style pattern <SERVICE>. Can be
+    /// given several times.
+    #[clap(long, value_name = "SERVICE")]
+    pub exclude_service: Vec<String>,
+
+    /// Read the svlogd formatted log file <FILE> instead of the log
+    /// directories, "-" reads from stdin. Can be given several times.
+    #[clap(
+        long = "file",
+        value_name = "FILE",
+        conflicts_with_all = &["exclude_service", "list", "services"],
+    )]
+    pub files: Vec<String>,
+
+    /// Only read the entries added since the last run with the same <FILE>
+    /// and store the positions in the log files in <FILE> afterwards (in
+    /// follow mode whenever new entries were read). Rotated log files are
+    /// recognized, so running e.g. "svlog --cursor-file c.json forward ..."
+    /// from cron sends every entry once.
+    #[clap(
+        long = "cursor-file",
+        value_name = "FILE",
+        conflicts_with_all = &["list", "list_boots"],
+    )]
+    pub cursor_file: Option<PathBuf>,
 
     /// Follow the services for new logs.
     #[clap(short, long, conflicts_with = "boot_offset")]
     pub follow: bool,
 
+    /// In follow mode, run the command of the next --exec for the new entries
+    /// matching the regular expression <REGEX>. Can be given several times.
+    #[clap(
+        long = "on-match",
+        value_name = "REGEX",
+        value_parser = parse_regex,
+        requires = "follow"
+    )]
+    pub on_match: Vec<String>,
+
+    /// Shell command run for the new entries matching the --on-match before
+    /// it. The formatted entry is passed on stdin and its fields in the
+    /// environment variables SVLOG_TIMESTAMP, SVLOG_SERVICE, SVLOG_LABEL,
+    /// SVLOG_FACILITY, SVLOG_PRIORITY (0-7) and SVLOG_MESSAGE.
+    #[clap(long = "exec", value_name = "CMD", requires = "on_match")]
+    pub exec: Vec<String>,
+
+    /// Read --on-match/--exec and --rate-alert rules from <FILE>. Each rule
+    /// consists of an "on-match REGEX" line followed by an "exec CMD" line or
+    /// a "rate RULE" line optionally followed by an "exec CMD" line run for
+    /// the alerts. Lines starting with "#" are ignored.
+    #[clap(long = "rules", value_name = "FILE", requires = "follow")]
+    pub rules: Option<PathBuf>,
+
+    /// In follow mode, print an alert when a service has more new entries
+    /// within a sliding window than allowed by <RULE>, e.g. "kernel:..err:20/1m"
+    /// for more than 20 errors per minute from "kernel". The format is
+    /// [SERVICE:][PRIORITY:]LIMIT/WINDOW, where SERVICE is a pattern like
+    /// "net*" and WINDOW is given in s, m, h or d. Use --rules to run a command
+    /// for alerts. Can be given several times.
+    #[clap(
+        long = "rate-alert",
+        value_name = "RULE",
+        value_parser = parse_rate_rule,
+        requires = "follow"
+    )]
+    pub rate_alert: Vec<RateRule>,
+
+    /// Don't run the command of a rule again within <SECONDS> after it was
+    /// run, matching entries in between are ignored
+    #[clap(long = "debounce", value_name = "SECONDS", default_value = "0")]
+    pub debounce: u64,
+
+    /// Maximum number of commands (see --exec) running at the same time,
+    /// further commands wait until one finishes
+    #[clap(long = "max-exec", value_name = "N", default_value = "4")]
+    pub max_exec: usize,
+
     /// Set the filter (--match) case insensitive
     #[clap(short = 'i', long = "case-insensitive")]
     pub case_insensitive: bool,
@@ -51,39 +159,78 @@ pub struct Args {
     #[clap(short, long, default_value = "0")]
     pub jobs: usize,
 
-    /// List available services and exit
+    /// List available services with the number and size of their log files,
+    /// the number of lines in "current", the time of the oldest and newest
+    /// entry and whether unprocessed (*.u) files exist and exit
     #[clap(short, long)]
     pub list: bool,
 
+    /// List the boots with their OFFSET (see --boot-offset), start and end
+    /// time, duration and number of log lines and exit
+    #[clap(
+        long,
+        conflicts_with_all = &["boot", "boot_offset", "follow", "lines", "list", "since", "until"],
+    )]
+    pub list_boots: bool,
+
     /// Only show entries which match the regular expression <REGEX>
-    #[clap(short = 'm', long = "match", required = false, value_name = "REGEX")]
+    #[clap(
+        short = 'm',
+        long = "match",
+        required = false,
+        value_name = "REGEX",
+        value_parser = parse_regex
+    )]
     pub filter: Option<String>,
 
     /// Limit the number of lines shown. <N> may be a positive integer or "all".
-    /// If --follow is used, a default value of 10 is used.
+    /// If --follow is used without --cursor-file, a default value of 10 is
+    /// used.
     #[clap(
         short = 'n',
         long = "lines",
         value_name = "N",
-        conflicts_with_all = &["boot_offset", "boot", "since", "until"], //
-        default_value_if("follow", ArgPredicate::Equals("true".into()), Some("10")),
+        conflicts_with_all = &["since", "until"],
+        default_value_ifs([
+            ("cursor_file", ArgPredicate::IsPresent, None),
+            ("follow", ArgPredicate::Equals("true".into()), Some("10")),
+        ]),
     )]
     pub lines: Option<usize>,
 
+    /// Don't print a "-- Boot N: <START> --" line between log entries of
+    /// different boots.
+    #[clap(long = "no-boot-separators")]
+    pub no_boot_separators: bool,
+
     /// Just print to stdout and don't pipe the output into a pager
     #[clap(long = "no-pager")]
     pub no_pager: bool,
 
     /// Only show logs from a certain boot. An OFFSET of 0 means the current
-    /// boot (like --boot), an OFFSET of 1 the previous one and so on.
-    #[cfg(target_env = "gnu")]
+    /// boot (like --boot), an OFFSET of 1 the previous one and so on. Several
+    /// boots can be selected with a range (e.g. "1..3" or "..2") or a comma
+    /// separated list (e.g. "0,2..4"). Use --list-boots to show the available
+    /// boots. If combined with --since/--until, only the overlapping time is
+    /// shown.
     #[clap(
         short = 'o',
         long,
         value_name = "OFFSET",
-        conflicts_with_all = &["boot", "lines", "follow"],
+        value_parser = parse_boot_offsets,
+        conflicts_with_all = &["boot", "follow"],
+    )]
+    pub boot_offset: Option<BootOffsets>,
+
+    /// Output format of the log entries: "text" or "json" (one object per
+    /// line with "type" "entry" or "boot").
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        value_parser = parse_output_format,
+        default_value = "text"
     )]
-    pub boot_offset: Option<usize>,
+    pub output: OutputFormat,
 
     /// Specify the priority (e.g. "warn") or a range of priorities
     /// (e.g. "warn..5") to display. A priority can be specified either as text
@@ -92,42 +239,226 @@ pub struct Args {
     #[clap(short, long, value_parser = parse_priorities, default_value = "0..7")]
     pub priority: (LogPriority, LogPriority),
 
+    /// Don't print the entries, only report with the exit status whether any
+    /// entry matched the filters
+    #[clap(short, long, conflicts_with_all = &["follow", "list", "list_boots"])]
+    pub quiet: bool,
+
     /// Only consider logs from this time on forward. Possible values: "today",
     /// "yesterday", "YYYY-MM-DD HH:MM:SS", "YYYY-MM-DD HH:MM", "YYYY-MM-DD",
-    /// "HH:MM:SS", "HH:MM". If no date is specified, "today" is assumed. If no
-    /// hour/minute/second is specified, 0 is assumed. The timestamps are in
-    /// locatime unless the --utc option is set.
+    /// "HH:MM:SS", "HH:MM" or a time relative to now like "-30s", "-5m", "-2h"
+    /// or "-1d". If no date is specified, "today" is assumed. If no
+    /// hour/minute/second is specified, 0 is assumed. The timestamps are in the
+    /// display timezone (see --tz and --utc).
     #[clap(
         short,
         long,
         value_parser = parse_ndt_since,
-        conflicts_with_all = &["boot", "boot_offset", "lines", "follow"],
+        allow_hyphen_values = true,
+        conflicts_with_all = &["lines", "follow"],
     )]
-    pub since: Option<NaiveDateTime>,
+    pub since: Option<TimePoint>,
 
     /// Only consider logs until (and including) this time. Possible values:
     /// "today", "yesterday", "YYYY-MM-DD HH:MM:SS", "YYYY-MM-DD HH:MM",
-    /// "YYYY-MM-DD", "HH:MM:SS", "HH:MM". If no date is specified, "today" is
-    /// assumed. If no hour/minute/second is specified, 0 is assumed. The
-    /// timestamps are in locatime unless the --utc option is set.
+    /// "YYYY-MM-DD", "HH:MM:SS", "HH:MM" or a time relative to now like "-5m"
+    /// (see --since). If no date is specified, "today" is assumed. If no
+    /// hour/minute/second is specified, 0 is assumed. The timestamps are in the
+    /// display timezone (see --tz and --utc).
     #[clap(
         short,
         long,
         value_parser = parse_ndt_until,
-        conflicts_with_all = &["boot", "boot_offset", "lines", "follow"],
+        allow_hyphen_values = true,
+        conflicts_with_all = &["lines", "follow"],
     )]
-    pub until: Option<NaiveDateTime>,
+    pub until: Option<TimePoint>,
+
+    /// Use the timezone <TZ> (e.g. "Europe/Zurich") for timestamps instead of
+    /// localtime (including timestamps in other options). If not set, the TZ
+    /// environment variable is honoured.
+    #[clap(long, value_name = "TZ", value_parser = parse_tz, conflicts_with = "utc")]
+    pub tz: Option<Tz>,
 
     /// Use UTC for timestamps instead of localtime (including timestamps in
     /// other options).
     #[clap(long = "utc")]
     pub utc: bool,
 
-    /// Services to log (all by default)
+    /// The wtmp file to read the boot records of --boot-offset from.
+    #[clap(
+        long,
+        value_name = "FILE",
+        default_value = "/var/log/wtmp",
+        env = "SVLOG_WTMP"
+    )]
+    pub wtmp: String,
+
+    /// Services to log (all by default). Services may be shell-style patterns
+    /// (e.g. 'net*'), a leading "^" excludes the matching services instead
+    /// (e.g. ^cron).
     #[clap()]
     pub services: Vec<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+// NOTE: the options to select the logs are given before the subcommand, e.g.
+// "svlog -b kernel export -o bundle.tar.zst".
+#[derive(Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Write the selected entries per service in svlogd layout together with a
+    /// manifest (hostname, timezone, boots and the query) to a tar archive.
+    /// Options to select the entries go before "export", e.g. "svlog -b kernel
+    /// export -o bundle.tar.zst". Read the bundle with "svlog -d <FILE>".
+    Export {
+        /// Path of the bundle. It is compressed with zstd or gzip if it ends
+        /// with ".zst"/".tzst" or ".gz"/".tgz".
+        #[clap(short = 'o', long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Count the selected entries per service and report the result like a
+    /// Nagios/Icinga plugin: the status is OK, WARNING or CRITICAL (exit
+    /// status 0, 1 or 2) if no service has more matching entries than the
+    /// thresholds and UNKNOWN (3) on errors. Options to select the entries go
+    /// before "check", e.g. "svlog -s -5m -p ..err check -w 5 -c 10".
+    Check {
+        /// Report WARNING if a service has more than <N> matching entries
+        #[clap(short = 'w', long, value_name = "N")]
+        warning: Option<usize>,
+
+        /// Report CRITICAL if a service has more than <N> matching entries.
+        /// Without thresholds, any matching entry is critical.
+        #[clap(short = 'c', long, value_name = "N")]
+        critical: Option<usize>,
+    },
+    /// Send the selected entries to a syslog server, e.g. "svlog -f -p ..warn
+    /// forward tcp://logs.example.com". The facility and priority of an entry
+    /// become the priority of the message and the service its app name (RFC
+    /// 5424) or tag (RFC 3164). Options to select the entries go before
+    /// "forward". With --follow, new entries are sent until svlog is stopped.
+    Forward {
+        /// Address of the server as udp://, tcp:// or tls://HOST[:PORT]. The
+        /// port defaults to 514 (udp), 601 (tcp) or 6514 (tls).
+        #[clap(value_name = "TARGET", value_parser = parse_syslog_target)]
+        target: SyslogTarget,
+
+        /// Format of the messages: rfc5424 or rfc3164. Messages sent over TCP
+        /// or TLS are prefixed with their length (RFC 5424) or end with a
+        /// newline (RFC 3164).
+        #[clap(
+            long = "format",
+            value_name = "FORMAT",
+            default_value = "rfc5424",
+            value_parser = parse_syslog_format
+        )]
+        format: SyslogFormat,
+
+        /// Hostname in the messages instead of the one of this machine (or
+        /// of the bundle)
+        #[clap(long = "hostname", value_name = "NAME")]
+        hostname: Option<String>,
+
+        /// Verify the certificate of the server with the CA certificates in
+        /// <FILE> (PEM) instead of the ones of the system
+        #[clap(long = "ca-file", value_name = "FILE")]
+        ca_file: Option<PathBuf>,
+    },
+    /// Push the selected entries to Grafana Loki, e.g. "svlog -f loki
+    /// http://localhost:3100". The entries are labeled with their service,
+    /// facility, priority and host. Options to select the entries go before
+    /// "loki". With --follow, new entries are pushed until svlog is stopped;
+    /// with --cursor-file, the cursor only advances after a successful push.
+    Loki {
+        /// URL of Loki as http:// or https://HOST[:PORT][/PATH]. The path
+        /// defaults to /loki/api/v1/push.
+        #[clap(value_name = "URL", value_parser = parse_loki_url)]
+        url: String,
+
+        /// Push at most <N> entries per request
+        #[clap(long = "batch-size", value_name = "N", default_value = "1000")]
+        batch_size: usize,
+
+        /// Retry a push failing with a connection or server error <N> times,
+        /// waiting 0.5s at first and twice as long after each attempt (up to
+        /// 30s). With --follow, pushes are retried until they succeed.
+        #[clap(long = "retries", value_name = "N", default_value = "5")]
+        retries: u32,
+
+        /// Tenant sent as X-Scope-OrgID header
+        #[clap(long = "tenant", value_name = "ID")]
+        tenant: Option<String>,
+
+        /// Value of the "host" label instead of the hostname of this machine
+        /// (or of the bundle)
+        #[clap(long = "hostname", value_name = "NAME")]
+        hostname: Option<String>,
+
+        /// Verify the certificate of the server with the CA certificates in
+        /// <FILE> (PEM) instead of the ones of the system
+        #[clap(long = "ca-file", value_name = "FILE")]
+        ca_file: Option<PathBuf>,
+    },
+    /// Serve the logs over HTTP, e.g. to browse them through an SSH tunnel.
+    /// GET /api/entries returns the entries as JSON, /api/follow streams new
+    /// ones as Server-Sent Events and /api/services lists the services. The
+    /// entries are selected with the query parameters service, priority,
+    /// since, until, match, case_insensitive, lines, boot, boot_offset and
+    /// exclude_service, which take the values of the options of the same
+    /// name, e.g. "/api/entries?service=kernel&priority=..err&since=-1h".
+    /// Options to choose the log files and the timezone go before "serve".
+    Serve {
+        /// Address to listen on. The API has no authentication, so keep it on
+        /// localhost unless the network is trusted.
+        #[clap(long = "listen", value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+
+        /// Follow the logs for at most <N> clients of /api/follow at once
+        #[clap(long = "max-streams", value_name = "N", default_value = "16")]
+        max_streams: usize,
+    },
 }
 
+impl Args {
+    // Returns the first log directory which the boots are read from.
+    pub fn log_dir(&self) -> &str {
+        &self.log_dirs[0].path
+    }
+
+    // Returns whether the labels of the log directories are shown.
+    pub fn show_labels(&self) -> bool {
+        self.log_dirs.len() > 1 || self.log_dirs.iter().any(|dir| dir.label.is_some())
+    }
+}
+
+// A log directory given with --log-dir and its optional label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogDir {
+    pub label: Option<String>,
+    pub path: String,
+}
+
+impl LogDir {
+    // Returns the label or the path if it has none.
+    pub fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.path)
+    }
+}
+
+// A time given with --since or --until. Absolute times are in the display
+// timezone, relative times (e.g. "-5m") are already in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimePoint {
+    Local(NaiveDateTime),
+    Utc(NaiveDateTime),
+}
+
+// Ranges of the boots selected with --boot-offset. They are only expanded once
+// the number of boots is known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BootOffsets(pub Vec<RangeInclusive<usize>>);
+
 #[derive(Debug)]
 struct InvalidArgError(String);
 impl fmt::Display for InvalidArgError {
@@ -140,38 +471,140 @@ impl Error for InvalidArgError {}
 fn parse_priorities(
     s: &str,
 ) -> Result<(LogPriority, LogPriority), Box<dyn Error + Send + Sync + 'static>> {
-    let priorities: Vec<&str> = s.split("..").collect();
-    let return_value = match priorities.len() {
-        1 => {
-            let p = LogPriority::parse(priorities.first().unwrap());
-            p.map(|p| (p, p))
-        }
-        2 => {
-            let p1 = *priorities.first().unwrap();
-            let priority_1 = match p1 {
-                "" => Some(LogPriority::min()),
-                _ => LogPriority::parse(p1),
-            };
-            let p2 = *priorities.last().unwrap();
-            let priority_2 = match p2 {
-                "" => Some(LogPriority::max()),
-                _ => LogPriority::parse(p2),
-            };
-            match (priority_1, priority_2) {
-                (Some(priority_1), Some(priority_2)) => Some((priority_1, priority_2)),
-                _ => None,
-            }
+    LogPriority::parse_range(s)
+        .ok_or_else(|| Box::new(InvalidArgError(format!("Invalid priority \"{}\"", s))).into())
+}
+
+fn parse_rate_rule(s: &str) -> Result<RateRule, Box<dyn Error + Send + Sync + 'static>> {
+    RateRule::parse(s).ok_or_else(|| {
+        Box::new(InvalidArgError(format!(
+            "Invalid rate rule \"{}\" (e.g. \"kernel:..err:20/1m\")",
+            s
+        )))
+        .into()
+    })
+}
+
+// Checks that s is a valid regex, it is compiled again when the filters are
+// built.
+fn parse_regex(s: &str) -> Result<String, Box<dyn Error + Send + Sync + 'static>> {
+    match regex::build_regex(s, false) {
+        Ok(_) => Ok(s.to_string()),
+        Err(e) => Err(Box::new(InvalidArgError(e.to_string()))),
+    }
+}
+
+fn parse_boot_offsets(s: &str) -> Result<BootOffsets, Box<dyn Error + Send + Sync + 'static>> {
+    let invalid = || Box::new(InvalidArgError(format!("Invalid boot offset \"{}\"", s)));
+    let mut offsets = Vec::new();
+    for part in s.split(',') {
+        let range: Vec<&str> = part.split("..").collect();
+        let (from, to) = match range[..] {
+            [offset] => (offset, offset),
+            ["", to] => ("0", to),
+            [from, to] => (from, to),
+            _ => return Err(invalid()),
+        };
+        let from: usize = from.trim().parse().map_err(|_| invalid())?;
+        let to: usize = to.trim().parse().map_err(|_| invalid())?;
+        if from > to {
+            return Err(invalid());
         }
-        _ => None,
+        offsets.push(from..=to);
+    }
+    Ok(BootOffsets(offsets))
+}
+
+// Parses "LABEL=DIR" or "DIR". Paths containing "=" are only split if the part
+// before it doesn't look like a path.
+fn parse_log_dir(s: &str) -> Result<LogDir, Box<dyn Error + Send + Sync + 'static>> {
+    let log_dir = match s.split_once('=') {
+        Some((label, path)) if !label.is_empty() && !label.contains('/') => LogDir {
+            label: Some(label.to_string()),
+            path: path.to_string(),
+        },
+        _ => LogDir {
+            label: None,
+            path: s.to_string(),
+        },
     };
-    if let Some(return_value) = return_value {
-        Ok(return_value)
-    } else {
-        Err(Box::new(InvalidArgError(format!(
-            "Invalid priority \"{}\"",
+    if log_dir.path.is_empty() {
+        return Err(Box::new(InvalidArgError(format!(
+            "Invalid log directory \"{}\"",
             s
-        ))))
+        ))));
     }
+    Ok(log_dir)
+}
+
+fn parse_boot_source(s: &str) -> Result<BootSource, Box<dyn Error + Send + Sync + 'static>> {
+    BootSource::parse(s).ok_or_else(|| {
+        Box::new(InvalidArgError(format!(
+            "Invalid boot source \"{}\" (possible values: wtmp, logs, both)",
+            s
+        )))
+        .into()
+    })
+}
+
+fn parse_dst_policy(s: &str) -> Result<DstPolicy, Box<dyn Error + Send + Sync + 'static>> {
+    DstPolicy::parse(s).ok_or_else(|| {
+        Box::new(InvalidArgError(format!(
+            "Invalid policy \"{}\" (possible values: earliest, latest, error)",
+            s
+        )))
+        .into()
+    })
+}
+
+fn parse_output_format(s: &str) -> Result<OutputFormat, Box<dyn Error + Send + Sync + 'static>> {
+    OutputFormat::parse(s).ok_or_else(|| {
+        Box::new(InvalidArgError(format!(
+            "Invalid output format \"{}\" (possible values: text, json)",
+            s
+        )))
+        .into()
+    })
+}
+
+fn parse_syslog_target(s: &str) -> Result<SyslogTarget, Box<dyn Error + Send + Sync + 'static>> {
+    SyslogTarget::parse(s).ok_or_else(|| {
+        Box::new(InvalidArgError(format!(
+            "Invalid target \"{}\" (e.g. udp://localhost, tcp://localhost:601)",
+            s
+        )))
+        .into()
+    })
+}
+
+fn parse_loki_url(s: &str) -> Result<String, Box<dyn Error + Send + Sync + 'static>> {
+    let address = s
+        .strip_prefix("http://")
+        .or_else(|| s.strip_prefix("https://"))
+        .filter(|address| !address.is_empty() && !address.starts_with('/'));
+    match address {
+        Some(address) if address.trim_end_matches('/').contains('/') => Ok(s.to_string()),
+        Some(_) => Ok(format!("{}/loki/api/v1/push", s.trim_end_matches('/'))),
+        None => Err(Box::new(InvalidArgError(format!(
+            "Invalid URL \"{}\" (e.g. http://localhost:3100)",
+            s
+        )))),
+    }
+}
+
+fn parse_syslog_format(s: &str) -> Result<SyslogFormat, Box<dyn Error + Send + Sync + 'static>> {
+    SyslogFormat::parse(s).ok_or_else(|| {
+        Box::new(InvalidArgError(format!(
+            "Invalid format \"{}\" (possible values: rfc5424, rfc3164)",
+            s
+        )))
+        .into()
+    })
+}
+
+fn parse_tz(s: &str) -> Result<Tz, Box<dyn Error + Send + Sync + 'static>> {
+    s.parse::<Tz>()
+        .map_err(|_| Box::new(InvalidArgError(format!("Invalid timezone \"{}\"", s))).into())
 }
 
 // until-time represents the last point in time included in the logs
@@ -193,14 +626,50 @@ impl fmt::Display for TimeError {
 }
 impl Error for TimeError {}
 
-fn parse_ndt_until(s: &str) -> Result<NaiveDateTime, Box<dyn Error + Send + Sync + 'static>> {
+fn parse_ndt_until(s: &str) -> Result<TimePoint, Box<dyn Error + Send + Sync + 'static>> {
+    if let Some(time) = parse_relative_time(s)? {
+        return Ok(TimePoint::Utc(time));
+    }
     let time_point = parse_ndt(s)?;
-    Ok(time_point.0.add(time_point.1).sub(Duration::nanoseconds(1)))
+    Ok(TimePoint::Local(
+        time_point.0.add(time_point.1).sub(Duration::nanoseconds(1)),
+    ))
 }
 
-fn parse_ndt_since(s: &str) -> Result<NaiveDateTime, Box<dyn Error + Send + Sync + 'static>> {
+fn parse_ndt_since(s: &str) -> Result<TimePoint, Box<dyn Error + Send + Sync + 'static>> {
+    if let Some(time) = parse_relative_time(s)? {
+        return Ok(TimePoint::Utc(time));
+    }
     let time_point = parse_ndt(s)?;
-    Ok(time_point.0)
+    Ok(TimePoint::Local(time_point.0))
+}
+
+// Parses times relative to now like "-5m" (seconds, minutes, hours or days).
+// Returns None if the time isn't relative.
+fn parse_relative_time(
+    s: &str,
+) -> Result<Option<NaiveDateTime>, Box<dyn Error + Send + Sync + 'static>> {
+    let captures = match regex::RE_RELATIVE_TIME.captures(s) {
+        Some(captures) => captures,
+        None => return Ok(None),
+    };
+    let time = captures[1]
+        .parse::<i64>()
+        .ok()
+        .and_then(|amount| match &captures[2] {
+            "s" => Duration::try_seconds(amount),
+            "m" => Duration::try_minutes(amount),
+            "h" => Duration::try_hours(amount),
+            _ => Duration::try_days(amount),
+        })
+        .and_then(|duration| Utc::now().naive_utc().checked_sub_signed(duration));
+    match time {
+        Some(time) => Ok(Some(time)),
+        None => Err(Box::new(TimeError(format!(
+            "Time \"{}\" is out of range",
+            s
+        )))),
+    }
 }
 
 fn parse_ndt(s: &str) -> Result<(NaiveDateTime, Duration), Box<dyn Error + Send + Sync + 'static>> {
@@ -0,0 +1,278 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::NaiveDateTime;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use svlog_util::{Boot, SvLogResult, TimeWindow, WriteBundleSnafu};
+
+use crate::LogLine;
+
+/// Name of the manifest in bundles.
+pub static MANIFEST_NAME: &str = "manifest.json";
+/// Version of the bundle format written by [`write_bundle`].
+pub static BUNDLE_VERSION: u32 = 1;
+/// Service of log lines without service (e.g. read from stdin)
+static UNKNOWN_SERVICE: &str = "unknown";
+
+/// Description of a bundle, stored as "manifest.json" next to the service
+/// directories.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the bundle format.
+    pub version: u32,
+    /// Version of svlog which wrote the bundle.
+    pub svlog_version: String,
+    /// Time the bundle was written in UTC.
+    pub created: NaiveDateTime,
+    pub hostname: Option<String>,
+    /// Local timezone of the machine, e.g. "Europe/Zurich".
+    pub timezone: String,
+    /// Boots of the machine ordered from the current to the first one.
+    pub boots: Vec<Boot>,
+    /// Query which selected the entries of the bundle.
+    pub query: BundleQuery,
+}
+
+/// Filters used to select the entries of a bundle.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct BundleQuery {
+    /// Command line arguments of svlog.
+    pub args: Vec<String>,
+    pub services: Vec<String>,
+    /// Time windows in UTC.
+    pub windows: Vec<TimeWindow>,
+    pub min_priority: u8,
+    pub max_priority: u8,
+    pub pattern: Option<String>,
+}
+
+/// Compression of an archive, chosen by the extension of its path
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum Codec {
+    Plain,
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    pub(crate) fn from_path(path: &Path) -> Self {
+        let name = path.to_string_lossy();
+        if name.ends_with(".gz") || name.ends_with(".tgz") {
+            Codec::Gzip
+        } else if name.ends_with(".zst") || name.ends_with(".tzst") {
+            Codec::Zstd
+        } else {
+            Codec::Plain
+        }
+    }
+}
+
+/// Writes the log lines in the svlogd layout (`<service>/current` or
+/// `<label>/<service>/current` for labeled lines) together with the manifest
+/// to a tar archive. The archive is compressed with zstd or gzip if the path
+/// ends with ".zst"/".tzst" or ".gz"/".tgz". Fails if a label or service
+/// isn't a valid directory name.
+pub fn write_bundle(
+    path: &Path,
+    log_lines: &BTreeSet<LogLine>,
+    manifest: &Manifest,
+) -> SvLogResult<()> {
+    let write = || -> io::Result<()> {
+        let services = service_files(log_lines)?;
+        let file = File::create(path)?;
+        match Codec::from_path(path) {
+            Codec::Zstd => {
+                write_tar(zstd::Encoder::new(file, 0)?, &services, manifest)?.finish()?;
+            }
+            Codec::Gzip => {
+                let encoder = GzEncoder::new(file, Compression::default());
+                write_tar(encoder, &services, manifest)?.finish()?;
+            }
+            Codec::Plain => write_tar(file, &services, manifest)?.flush()?,
+        }
+        Ok(())
+    };
+    write().context(WriteBundleSnafu {
+        path: format!("{:?}", path),
+    })
+}
+
+/// Returns the content of the "current" file of each service directory.
+fn service_files(log_lines: &BTreeSet<LogLine>) -> io::Result<BTreeMap<String, String>> {
+    let mut services: BTreeMap<String, String> = BTreeMap::new();
+    for log_line in log_lines {
+        let service = match log_line.service() {
+            "" => UNKNOWN_SERVICE,
+            service => dir_name(service)?,
+        };
+        // NOTE: labeled lines are kept apart in a directory per label
+        let service_dir = match log_line.label() {
+            Some(label) => format!("{}/{service}", dir_name(label)?),
+            None => service.to_string(),
+        };
+        let content = services.entry(service_dir).or_default();
+        content.push_str(&log_line.to_string());
+        content.push('\n');
+    }
+    Ok(services)
+}
+
+/// Checks that the name can be used as directory in the archive, i.e. that it
+/// can't escape the directory it is extracted to.
+fn dir_name(name: &str) -> io::Result<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid directory name \"{}\"", name),
+        ));
+    }
+    Ok(name)
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    services: &BTreeMap<String, String>,
+    manifest: &Manifest,
+) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    let manifest = serde_json::to_string_pretty(manifest)?;
+    append_file(&mut builder, MANIFEST_NAME, manifest.as_bytes())?;
+    for (service, content) in services {
+        append_file(
+            &mut builder,
+            &format!("{service}/current"),
+            content.as_bytes(),
+        )?;
+    }
+    builder.into_inner()
+}
+
+fn append_file<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    content: &[u8],
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+    header.set_mtime(now.map_or(0, |now| now.as_secs()));
+    header.set_cksum();
+    builder.append_data(&mut header, path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{read_log_lines, ArchiveSource, LogFilterSettings, LogSource, MemorySource};
+
+    static ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+    static GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+    fn manifest() -> Manifest {
+        let start = NaiveDateTime::parse_from_str("2022-09-01 13:30:00", "%Y-%m-%d %H:%M:%S");
+        Manifest {
+            version: BUNDLE_VERSION,
+            svlog_version: "0.6.3".to_string(),
+            created: start.unwrap(),
+            hostname: Some("void".to_string()),
+            timezone: "Europe/Zurich".to_string(),
+            boots: vec![Boot {
+                start: start.unwrap(),
+                end: None,
+            }],
+            query: BundleQuery {
+                args: vec!["-b".to_string()],
+                services: Vec::new(),
+                windows: vec![TimeWindow::default()],
+                min_priority: 0,
+                max_priority: 7,
+                pattern: None,
+            },
+        }
+    }
+
+    fn read(sources: Vec<Box<dyn LogSource>>) -> Vec<String> {
+        read_log_lines(&sources, &LogFilterSettings::default())
+            .unwrap()
+            .0
+            .iter()
+            .map(|log_line| format!("{} {}", log_line.service(), log_line))
+            .collect()
+    }
+
+    /// Writes and reads a bundle, the magic bytes show the codec it was written
+    /// with.
+    fn round_trip(extension: &str, magic: &[u8]) {
+        let path =
+            std::env::temp_dir().join(format!("{}_bundle.{}", std::process::id(), extension));
+        let sources: Vec<Box<dyn LogSource>> = vec![
+            Box::new(MemorySource::new(
+                "kernel",
+                "2022-09-01T13:32:00.00000 kern.info: k",
+            )),
+            Box::new(MemorySource::new(
+                "daemon",
+                "2022-09-01T13:33:00.00000 daemon.info: d",
+            )),
+        ];
+        let (log_lines, _) = read_log_lines(&sources, &LogFilterSettings::default()).unwrap();
+        write_bundle(&path, &log_lines, &manifest()).unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(magic));
+
+        let source = ArchiveSource::new(path.clone());
+        assert_eq!(source.manifest().unwrap(), Some(manifest()));
+        assert_eq!(
+            read(vec![Box::new(source)]),
+            vec![
+                "kernel 2022-09-01T13:32:00.00000 kern.info: k",
+                "daemon 2022-09-01T13:33:00.00000 daemon.info: d",
+            ]
+        );
+        let source = ArchiveSource::new(path.clone()).services(&["^k*".to_string()], &[]);
+        assert_eq!(read(vec![Box::new(source)]).len(), 1);
+        let source = ArchiveSource::new(path.clone()).services(&["x".to_string()], &[]);
+        assert!(source.streams().is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn round_trip_zstd() {
+        round_trip("tar.zst", ZSTD_MAGIC);
+        round_trip("tzst", ZSTD_MAGIC);
+    }
+
+    #[test]
+    fn round_trip_gzip() {
+        round_trip("tar.gz", GZIP_MAGIC);
+        round_trip("tgz", GZIP_MAGIC);
+    }
+
+    #[test]
+    fn round_trip_tar() {
+        round_trip("tar", MANIFEST_NAME.as_bytes());
+    }
+
+    #[test]
+    fn invalid_labels() {
+        let path = std::env::temp_dir().join(format!("{}_invalid.tar", std::process::id()));
+        for label in ["", ".", "..", "../etc", "a/b"] {
+            let log_line = LogLine::new("2022-09-01T13:32:00.00000 kern.info: k".to_string())
+                .unwrap()
+                .with_service(Arc::from("kernel"))
+                .with_label(Some(Arc::from(label)));
+            let log_lines = BTreeSet::from([log_line]);
+            assert!(write_bundle(&path, &log_lines, &manifest()).is_err());
+        }
+        assert!(!path.exists());
+    }
+}
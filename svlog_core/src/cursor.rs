@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use snafu::ensure;
+use svlog_util::{CursorSnafu, SvLogError, SvLogResult};
+
+use crate::LogLine;
+
+pub static CURSOR_VERSION: u32 = 1;
+
+/// Lines of svlogd are at most 1000 bytes long by default
+static MAX_LINE_LENGTH: u64 = 64 * 1024;
+
+/// Position up to which a log file was read.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FileCursor {
+    /// Inode of the file, which stays the same when svlogd renames "current"
+    /// while rotating the logs.
+    pub inode: u64,
+    /// Number of bytes read.
+    pub offset: u64,
+    /// Time of the last line read.
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+/// Positions of the log files read by the previous run, stored as JSON to
+/// read only the new entries the next time, see
+/// [`CursorSource`](crate::CursorSource). The paths of the files are
+/// absolute.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub version: u32,
+    pub files: BTreeMap<PathBuf, FileCursor>,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor {
+            version: CURSOR_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl Cursor {
+    /// Reads the cursor from a file. A missing file is an empty cursor, e.g.
+    /// on the first run.
+    pub fn load(path: &Path) -> SvLogResult<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Cursor::default()),
+            Err(e) => return Err(cursor_error(path, e)),
+        };
+        let cursor: Cursor = serde_json::from_str(&content).map_err(|e| cursor_error(path, e))?;
+        ensure!(
+            cursor.version == CURSOR_VERSION,
+            CursorSnafu {
+                path: path.display().to_string(),
+                message: format!("unsupported version {}", cursor.version),
+            }
+        );
+        Ok(cursor)
+    }
+
+    /// Writes the cursor to a file. The file is replaced at once, so it is
+    /// never left half written.
+    pub fn save(&self, path: &Path) -> SvLogResult<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let content = serde_json::to_string_pretty(self).expect("cursor is serializable");
+        fs::write(&tmp_path, content)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|e| cursor_error(path, e))
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&FileCursor> {
+        self.files.get(&absolute_path(path))
+    }
+
+    /// Sets the position of a file. The time of the last line is kept if no
+    /// line was read.
+    pub fn update(
+        &mut self,
+        path: &Path,
+        inode: u64,
+        offset: u64,
+        timestamp: Option<NaiveDateTime>,
+    ) {
+        let path = absolute_path(path);
+        let timestamp = timestamp.or_else(|| self.files.get(&path).and_then(|f| f.timestamp));
+        self.files.insert(
+            path,
+            FileCursor {
+                inode,
+                offset,
+                timestamp,
+            },
+        );
+    }
+}
+
+fn cursor_error(path: &Path, e: impl Display) -> SvLogError {
+    CursorSnafu {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    }
+    .build()
+}
+
+fn absolute_path(path: &Path) -> PathBuf {
+    std::env::current_dir().unwrap_or_default().join(path)
+}
+
+/// Returns the end of the last complete line before len (a line may be written
+/// at the moment) and its time.
+pub(crate) fn last_line(path: &Path, len: u64) -> (u64, Option<NaiveDateTime>) {
+    let start = len.saturating_sub(MAX_LINE_LENGTH);
+    let mut buf = Vec::new();
+    let read = File::open(path).and_then(|mut file| {
+        file.seek(SeekFrom::Start(start))?;
+        file.take(len - start).read_to_end(&mut buf)
+    });
+    if read.is_err() {
+        return (start, None);
+    }
+    let end = match buf.iter().rposition(|b| *b == b'\n') {
+        Some(i) => i + 1,
+        None => return (start, None),
+    };
+    let timestamp = String::from_utf8_lossy(&buf[..end])
+        .lines()
+        .rev()
+        .find_map(|line| LogLine::new(line.to_string()).ok())
+        .map(|log_line| *log_line.date());
+    (start + end as u64, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("svlog_cursor_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cursor.json");
+        assert_eq!(Cursor::load(&path).unwrap(), Cursor::default());
+        let mut cursor = Cursor::default();
+        let timestamp =
+            NaiveDateTime::parse_from_str("2022-09-02 13:39:53", "%Y-%m-%d %H:%M:%S").unwrap();
+        cursor.update(Path::new("/log/kernel/current"), 7, 42, Some(timestamp));
+        cursor.update(Path::new("/log/kernel/current"), 7, 50, None);
+        cursor.save(&path).unwrap();
+        let loaded = Cursor::load(&path).unwrap();
+        assert_eq!(
+            loaded.get(Path::new("/log/kernel/current")),
+            Some(&FileCursor {
+                inode: 7,
+                offset: 50,
+                timestamp: Some(timestamp),
+            })
+        );
+        fs::write(&path, "{}").unwrap();
+        assert!(Cursor::load(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn last_complete_line() {
+        let path = std::env::temp_dir().join(format!("svlog_last_{}", std::process::id()));
+        fs::write(
+            &path,
+            "2022-09-02T13:32:53.68972 kern.info: a\n2022-09-02T13:33:53.68972 kern.info: b\n",
+        )
+        .unwrap();
+        let line = |len| {
+            let (end, timestamp) = last_line(&path, len);
+            (end, timestamp.map(|t| t.format("%H:%M:%S").to_string()))
+        };
+        assert_eq!(line(78), (78, Some("13:33:53".to_string())));
+        // the second line is incomplete
+        assert_eq!(line(70), (39, Some("13:32:53".to_string())));
+        assert_eq!(line(10), (0, None));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::NaiveDateTime;
+use svlog_util::LogPriority;
+
+use crate::LogLine;
+
+/// A log line returned by a [`Query`](crate::Query).
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Entry {
+    /// Time of the log line in UTC.
+    pub timestamp: NaiveDateTime,
+    /// Name of the service, i.e. of the directory the log file is in.
+    pub service: String,
+    /// Label of the source, see [`LabeledSource`](crate::LabeledSource).
+    pub label: Option<String>,
+    /// Facility (e.g. "kern") if the line starts with "facility.priority".
+    pub facility: Option<String>,
+    /// Priority of the line, `LogPriority::max()` if it has none.
+    pub priority: LogPriority,
+    /// Content of the line without the "facility.priority:" prefix.
+    pub message: String,
+}
+
+impl Entry {
+    pub fn new(log_line: &LogLine) -> Self {
+        let content = log_line.content();
+        let (facility, message) = split_facility(content);
+        Entry {
+            timestamp: *log_line.date(),
+            service: log_line.service().to_string(),
+            label: log_line.label().map(str::to_string),
+            facility,
+            priority: *log_line.priority(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Splits the content of a log line like "kern.info: message" into the
+/// facility and the message. Lines without valid "facility.priority" prefix
+/// are returned as they are.
+fn split_facility(content: &str) -> (Option<String>, &str) {
+    let (prefix, rest) = content
+        .split_once(char::is_whitespace)
+        .unwrap_or((content, ""));
+    match prefix.trim_end_matches(':').rsplit_once('.') {
+        Some((facility, priority)) if LogPriority::parse(priority).is_some() => {
+            let facility = Some(facility.to_string()).filter(|f| !f.is_empty());
+            (facility, rest.trim_start())
+        }
+        _ => (None, content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_with_facility() {
+        assert_eq!(
+            split_facility("kern.info: hello fren!"),
+            (Some("kern".to_string()), "hello fren!")
+        );
+        assert_eq!(
+            split_facility("daemon.err\tx y"),
+            (Some("daemon".to_string()), "x y")
+        );
+    }
+
+    #[test]
+    fn split_without_facility() {
+        assert_eq!(split_facility(".err: x"), (None, "x"));
+        assert_eq!(split_facility("kernel: x y z"), (None, "kernel: x y z"));
+        assert_eq!(
+            split_facility("kernel.ambiguous: x"),
+            (None, "kernel.ambiguous: x")
+        );
+        assert_eq!(split_facility("kern.info"), (Some("kern".to_string()), ""));
+        assert_eq!(split_facility(""), (None, ""));
+    }
+}
@@ -0,0 +1,32 @@
+//! Library to read, filter and merge the log files written by socklog. It is
+//! used by the svlog binary and can be embedded in other tools, see [`Query`].
+
+mod bundle;
+mod cursor;
+mod entry;
+mod log_file;
+mod log_filter_settings;
+mod log_line;
+mod log_source;
+mod loki;
+mod query;
+mod rate;
+mod service_info;
+mod syslog;
+
+pub use bundle::{write_bundle, BundleQuery, Manifest, BUNDLE_VERSION, MANIFEST_NAME};
+pub use cursor::{Cursor, FileCursor, CURSOR_VERSION};
+pub use entry::Entry;
+pub use log_file::{service_name, LogFile};
+pub use log_filter_settings::LogFilterSettings;
+pub use log_line::LogLine;
+pub use log_source::{
+    read_log_lines, ArchiveSource, CursorSource, DirSource, FileSource, LabeledSource, LogSource,
+    LogStream, MemorySource, StdinSource,
+};
+pub use loki::loki_push_body;
+pub use query::Query;
+pub use rate::{Clock, RateAlert, RateMonitor, SystemClock};
+pub use service_info::{service_infos, ServiceInfo};
+pub use svlog_util::{LogPriority, RateRule, SvLogError, SvLogResult, TimeWindow};
+pub use syslog::format_syslog;
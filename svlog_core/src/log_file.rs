@@ -0,0 +1,44 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A log file which is followed, position is the number of bytes already read.
+pub struct LogFile {
+    pub path: PathBuf,
+    pub position: u64,
+    /// Label of the source the file belongs to
+    pub label: Option<Arc<str>>,
+}
+
+impl LogFile {
+    pub fn new(path: PathBuf, label: Option<Arc<str>>) -> Self {
+        LogFile {
+            path,
+            position: 0,
+            label,
+        }
+    }
+
+    pub fn service(&self) -> Arc<str> {
+        service_name(&self.path)
+    }
+
+    pub fn jump_to_end(&mut self) {
+        if let Ok(meta) = fs::metadata(&self.path) {
+            self.position = meta.len();
+        }
+    }
+}
+
+/// Returns the name of the service a log file belongs to, i.e. the name of the
+/// directory containing it.
+pub fn service_name(path: &Path) -> Arc<str> {
+    let name = path
+        .parent()
+        .and_then(Path::file_name)
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    Arc::from(name)
+}
@@ -0,0 +1,32 @@
+use regex::Regex;
+use svlog_util::{LogPriority, TimeWindow};
+
+use crate::LogLine;
+
+pub struct LogFilterSettings {
+    pub re: Option<Regex>,
+    /// A log line is shown if it is in any of the windows
+    pub windows: Vec<TimeWindow>,
+    pub min_priority: LogPriority,
+    pub max_priority: LogPriority,
+}
+
+impl LogFilterSettings {
+    /// Returns whether the log line passes all filters.
+    pub fn matches(&self, log_line: &LogLine) -> bool {
+        log_line.is_in(&self.windows)
+            && log_line.is_match(&self.re)
+            && log_line.has_priority(&self.min_priority, &self.max_priority)
+    }
+}
+
+impl Default for LogFilterSettings {
+    fn default() -> Self {
+        LogFilterSettings {
+            re: None,
+            windows: vec![TimeWindow::default()],
+            min_priority: LogPriority::min(),
+            max_priority: LogPriority::max(),
+        }
+    }
+}
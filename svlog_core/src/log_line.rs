@@ -1,13 +1,17 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
 
-use chrono::{LocalResult, NaiveDateTime, Offset, TimeZone, Timelike};
+use chrono::{NaiveDateTime, Offset, TimeZone, Timelike};
 use chrono_tz::Tz;
 use regex::Regex;
-use snafu::{ensure, ResultExt};
-use svlog_util::{LogPriority, ParsingChronoSnafu, ParsingLogLineSnafu, SvLogError, SvLogResult};
+use snafu::ResultExt;
+use svlog_util::{LogPriority, ParsingChronoSnafu, ParsingLogLineSnafu, SvLogResult, TimeWindow};
 
-// NOTE: Socklog timestamps only have 5 digits at the end. Therefore the last is always 0.
-static DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S.%f";
+/// Format of the socklog timestamps. They only have 5 fractional digits, "%.f"
+/// reads them as fraction of a second, so the dates hold the actual nanoseconds.
+static DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct LogLine {
@@ -15,12 +19,18 @@ pub struct LogLine {
     date_str: String,
     content: String,
     priority: LogPriority,
+    service: Arc<str>,
+    label: Option<Arc<str>>,
 }
 
 impl LogLine {
     pub fn new(line: String) -> SvLogResult<Self> {
-        ensure!(line.len() >= 25, ParsingLogLineSnafu { line });
-        let date_str: &str = &line[..25];
+        // NOTE: the date may contain multibyte characters, e.g. replaced
+        // invalid bytes
+        let date_str: &str = match line.get(..25) {
+            Some(date_str) => date_str,
+            None => return ParsingLogLineSnafu { line }.fail(),
+        };
         let date = NaiveDateTime::parse_from_str(date_str, DATE_FORMAT)
             .context(ParsingChronoSnafu { line: &line[..] })?;
         let content_str = line[25..].trim();
@@ -30,15 +40,19 @@ impl LogLine {
             date_str: date_str.to_string(),
             content: content_str.to_string(),
             priority,
+            service: Arc::from(""),
+            label: None,
         })
     }
 
-    pub fn is_between(&self, from: &Option<NaiveDateTime>, until: &Option<NaiveDateTime>) -> bool {
-        match (from, until) {
-            (Some(from), _) if self.date < *from => false,
-            (_, Some(until)) if self.date > *until => false,
-            _ => true,
-        }
+    /// Sets the service the log line belongs to.
+    pub fn with_service(mut self, service: Arc<str>) -> Self {
+        self.service = service;
+        self
+    }
+
+    pub fn is_in(&self, windows: &[TimeWindow]) -> bool {
+        windows.iter().any(|window| window.contains(&self.date))
     }
 
     pub fn has_priority(&self, min_priority: &LogPriority, max_priority: &LogPriority) -> bool {
@@ -49,28 +63,49 @@ impl LogLine {
         !matches!(re, Some(re) if !re.is_match(&self.content[..]))
     }
 
-    pub fn format_with_tz(&self, tz: &Option<Tz>) -> SvLogResult<String> {
+    pub fn date(&self) -> &NaiveDateTime {
+        &self.date
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn priority(&self) -> &LogPriority {
+        &self.priority
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Sets the label of the source the log line is from.
+    pub fn with_label(mut self, label: Option<Arc<str>>) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn format_with_tz(&self, tz: &Option<Tz>) -> String {
+        format!("{} {}", self.format_date(tz), self.content)
+    }
+
+    pub fn format_date(&self, tz: &Option<Tz>) -> String {
         if let Some(tz) = *tz {
+            // NOTE: the offset has to be taken from the converted time as the
+            // local time may be ambiguous around daylight saving transitions
             let local_time = tz.from_utc_datetime(&self.date);
-            let offset = tz.offset_from_local_datetime(&self.date);
-            if let LocalResult::Single(offset) = offset {
-                Ok(format!(
-                    "{}.{:0>5}{} {}",
-                    local_time.format("%Y-%m-%dT%H:%M:%S"),
-                    local_time.nanosecond(),
-                    offset.fix(),
-                    self.content,
-                ))
-            } else {
-                Err(SvLogError::TimeZoneError {
-                    message: format!(
-                        "Failed to compute tz offset for date \"{}\" and tz \"{}\"",
-                        self.date, tz
-                    ),
-                })
-            }
+            format!(
+                "{}.{:0>5}{}",
+                local_time.format("%Y-%m-%dT%H:%M:%S"),
+                local_time.nanosecond() / 10_000,
+                local_time.offset().fix(),
+            )
         } else {
-            Ok(format!("{}Z {}", self.date_str, self.content,))
+            format!("{}Z", self.date_str)
         }
     }
 
@@ -80,7 +115,7 @@ impl LogLine {
             .next()
             .unwrap_or("")
             .split('.')
-            .last()
+            .next_back()
             .unwrap_or("")
             .split(':')
             .next()
@@ -108,6 +143,7 @@ mod tests {
         assert_eq!(log_line.content, String::from("kern.info message"));
         assert_eq!(log_line.priority, LogPriority::parse("info").unwrap());
         assert_eq!(log_line.date_str, "2021-12-11T09:12:45.35141");
+        assert_eq!(log_line.date.nanosecond(), 351_410_000);
     }
 
     #[test]
@@ -132,6 +168,14 @@ mod tests {
         assert_eq!(log_line.date_str, "2021-12-11T09:12:45.35141");
     }
 
+    #[test]
+    fn parse_err_multibyte_timestamp() {
+        let ll_str = "2021-12-11T09:12:45.3514\u{FFFD} x";
+        assert!(LogLine::new(ll_str.to_string()).is_err());
+        let ll_str = String::from_utf8_lossy(b"2021-12-11T09:12:45.3514\xff x");
+        assert!(LogLine::new(ll_str.into_owned()).is_err());
+    }
+
     #[test]
     fn parse_err_too_short_timestamp() {
         let ll_str = "2021-12-11T09:12:45.3514";
@@ -183,6 +227,33 @@ mod tests {
         assert_eq!(log_line.date_str, "2021-12-11T09:12:45.35141");
     }
 
+    #[test]
+    fn format_europe_fold() {
+        let tz = Some(Tz::Europe__Zurich);
+        let ll = LogLine::new("2022-10-30T00:30:00.00000 x".to_string()).unwrap();
+        assert_eq!(ll.format_with_tz(&tz), "2022-10-30T02:30:00.00000+02:00 x");
+        let ll = LogLine::new("2022-10-30T01:30:00.00000 x".to_string()).unwrap();
+        assert_eq!(ll.format_with_tz(&tz), "2022-10-30T02:30:00.00000+01:00 x");
+    }
+
+    #[test]
+    fn format_us_fold() {
+        let tz = Some(Tz::America__New_York);
+        let ll = LogLine::new("2022-11-06T05:30:00.00000 x".to_string()).unwrap();
+        assert_eq!(ll.format_with_tz(&tz), "2022-11-06T01:30:00.00000-04:00 x");
+        let ll = LogLine::new("2022-11-06T06:30:00.00000 x".to_string()).unwrap();
+        assert_eq!(ll.format_with_tz(&tz), "2022-11-06T01:30:00.00000-05:00 x");
+    }
+
+    #[test]
+    fn format_us_gap() {
+        let tz = Some(Tz::America__New_York);
+        let ll = LogLine::new("2022-03-13T06:59:59.00000 x".to_string()).unwrap();
+        assert_eq!(ll.format_with_tz(&tz), "2022-03-13T01:59:59.00000-05:00 x");
+        let ll = LogLine::new("2022-03-13T07:00:00.00000 x".to_string()).unwrap();
+        assert_eq!(ll.format_with_tz(&tz), "2022-03-13T03:00:00.00000-04:00 x");
+    }
+
     #[test]
     fn priority_empty_string() {
         let s = "";
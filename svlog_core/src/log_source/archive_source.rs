@@ -0,0 +1,200 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use snafu::ResultExt;
+use svlog_util::{
+    services::filter_services, ManifestSnafu, OpenFileSnafu, ReadArchiveSnafu, SvLogResult,
+};
+
+use crate::{
+    bundle::{Codec, Manifest, MANIFEST_NAME},
+    log_file::service_name,
+    log_source::{LogSource, LogStream},
+};
+
+/// A tar archive (optionally compressed with gzip or zstd) of a socklog
+/// directory, e.g. a bundle written by [`write_bundle`](crate::write_bundle).
+/// The log files ("current", "*.s" and "*.u") are read from all service
+/// directories in the archive.
+pub struct ArchiveSource {
+    path: PathBuf,
+    patterns: Vec<String>,
+    excludes: Vec<String>,
+}
+
+impl ArchiveSource {
+    pub fn new(path: PathBuf) -> Self {
+        ArchiveSource {
+            path,
+            patterns: Vec::new(),
+            excludes: Vec::new(),
+        }
+    }
+
+    /// Only reads the services matching the patterns (all if empty) and none
+    /// of the excludes, like [`DirSource`](crate::DirSource).
+    pub fn services(mut self, patterns: &[String], excludes: &[String]) -> Self {
+        self.patterns = patterns.to_vec();
+        self.excludes = excludes.to_vec();
+        self
+    }
+
+    /// Returns whether the path has the extension of a supported archive.
+    pub fn is_archive(path: &Path) -> bool {
+        let name = path.to_string_lossy();
+        [".tar", ".tar.gz", ".tgz", ".tar.zst", ".tzst"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+    }
+
+    /// Reads the manifest of the archive, None if it isn't a bundle.
+    pub fn manifest(&self) -> SvLogResult<Option<Manifest>> {
+        let manifest = self
+            .files(|path| path == Path::new(MANIFEST_NAME))?
+            .into_iter()
+            .next();
+        manifest
+            .map(|(_, content)| {
+                serde_json::from_slice(&content).map_err(|e| {
+                    ManifestSnafu {
+                        message: format!("{:?}: {}", self.path, e),
+                    }
+                    .build()
+                })
+            })
+            .transpose()
+    }
+
+    fn open(&self) -> SvLogResult<Box<dyn Read>> {
+        let file = File::open(&self.path).context(OpenFileSnafu {
+            path: format!("{:?}", self.path),
+        })?;
+        match Codec::from_path(&self.path) {
+            Codec::Gzip => Ok(Box::new(GzDecoder::new(file))),
+            Codec::Zstd => {
+                let decoder = zstd::Decoder::new(file).context(ReadArchiveSnafu {
+                    path: format!("{:?}", self.path),
+                })?;
+                Ok(Box::new(decoder))
+            }
+            Codec::Plain => Ok(Box::new(file)),
+        }
+    }
+
+    /// Reads the regular files of the archive accepted by the filter into
+    /// memory, as tar archives can only be read sequentially.
+    fn files(&self, filter: impl Fn(&Path) -> bool) -> SvLogResult<Vec<(PathBuf, Vec<u8>)>> {
+        let context = || ReadArchiveSnafu {
+            path: format!("{:?}", self.path),
+        };
+        let mut archive = tar::Archive::new(self.open()?);
+        let mut files = Vec::new();
+        for entry in archive.entries().with_context(|_| context())? {
+            let mut entry = entry.with_context(|_| context())?;
+            let path = entry.path().with_context(|_| context())?.into_owned();
+            if !entry.header().entry_type().is_file() || !filter(&path) {
+                continue;
+            }
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .with_context(|_| context())?;
+            files.push((path, content));
+        }
+        Ok(files)
+    }
+}
+
+impl LogSource for ArchiveSource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        let mut files = self.files(is_log_file)?;
+        if !self.patterns.is_empty() || !self.excludes.is_empty() {
+            let mut services: Vec<String> = files
+                .iter()
+                .map(|(path, _)| service_name(path).to_string())
+                .collect();
+            services.sort();
+            services.dedup();
+            let selected = filter_services(services, &self.patterns, &self.excludes)?;
+            files.retain(|(path, _)| selected.contains(&service_name(path).to_string()));
+        }
+        Ok(files
+            .into_iter()
+            .map(|(path, content)| {
+                LogStream::from_reader(service_name(&path), Box::new(Cursor::new(content)))
+            })
+            .collect())
+    }
+}
+
+/// Returns whether the file is a log file written by svlogd.
+fn is_log_file(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name == "current" || name.ends_with(".s") || name.ends_with(".u"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    /// Writes a gzip compressed tar archive with the given files.
+    fn write_archive(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+        let encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (file_path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, file_path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn streams() {
+        let path = write_archive(
+            "svlog.tar.gz",
+            &[
+                (
+                    "socklog/kernel/current",
+                    "2022-09-01T13:32:00.00000 kern.info: k\n",
+                ),
+                (
+                    "socklog/daemon/@4000.s",
+                    "2022-09-01T13:33:00.00000 daemon.info: d\n",
+                ),
+                ("socklog/daemon/config", "ignored"),
+            ],
+        );
+        let streams = ArchiveSource::new(path.clone()).streams().unwrap();
+        std::fs::remove_file(path).unwrap();
+        let services: Vec<&str> = streams.iter().map(|s| &s.service[..]).collect();
+        assert_eq!(services, vec!["kernel", "daemon"]);
+    }
+
+    #[test]
+    fn is_archive() {
+        assert!(ArchiveSource::is_archive(Path::new("logs.tar.gz")));
+        assert!(ArchiveSource::is_archive(Path::new("logs.tgz")));
+        assert!(!ArchiveSource::is_archive(Path::new("/var/log/socklog/")));
+    }
+
+    #[test]
+    fn missing_archive() {
+        let source = ArchiveSource::new(PathBuf::from("/nonexistent.tar.gz"));
+        assert!(source.streams().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::{collections::HashMap, fs, os::unix::fs::MetadataExt, path::PathBuf, sync::Arc};
+
+use chrono::NaiveDateTime;
+use svlog_util::SvLogResult;
+
+use crate::{
+    cursor::{last_line, Cursor},
+    log_source::{Input, LogSource, LogStream},
+};
+
+/// Reads only the lines of another source which were added to its files
+/// since the positions of a [`Cursor`]. The files are measured when the source
+/// is created, lines written later are left for the next run. When svlogd
+/// rotated the logs in the meantime, the former "current" file is found by
+/// its inode. Other new files of a service are read from the time of the last
+/// line read.
+pub struct CursorSource {
+    source: Box<dyn LogSource>,
+    /// Byte range of each file to read and the time after which its lines are
+    /// new
+    ranges: HashMap<PathBuf, (u64, u64, Option<NaiveDateTime>)>,
+    cursor: Cursor,
+}
+
+impl CursorSource {
+    pub fn new(source: Box<dyn LogSource>, cursor: &Cursor) -> Self {
+        let mut ranges = HashMap::new();
+        let mut next_cursor = Cursor::default();
+        for path in source.files() {
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let inode = metadata.ino();
+            let (end, timestamp) = last_line(&path, metadata.len());
+            let current = cursor.get(&path.with_file_name("current"));
+            let (start, after) = match (cursor.get(&path), current) {
+                (Some(file), _) if file.inode == inode && file.offset <= end => (file.offset, None),
+                (_, Some(current)) if current.inode == inode && current.offset <= end => {
+                    (current.offset, None)
+                }
+                (_, Some(current)) => (0, current.timestamp),
+                (_, None) => (0, None),
+            };
+            ranges.insert(path.clone(), (start, end, after));
+            next_cursor.update(&path, inode, end, timestamp);
+        }
+        CursorSource {
+            source,
+            ranges,
+            cursor: next_cursor,
+        }
+    }
+
+    /// Returns the positions of the files after reading the source.
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+}
+
+impl LogSource for CursorSource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        Ok(self
+            .source
+            .streams()?
+            .into_iter()
+            .filter_map(|mut stream| {
+                let range = match &stream.input {
+                    Input::File(path) => self.ranges.get(path),
+                    _ => None,
+                };
+                if let Some((start, end, after)) = range {
+                    if start >= end {
+                        return None;
+                    }
+                    stream.range = Some((*start, *end));
+                    stream.after = *after;
+                }
+                Some(stream)
+            })
+            .collect())
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.source.files()
+    }
+
+    fn label(&self) -> Option<Arc<str>> {
+        self.source.label()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_log_lines, FileSource, LogFilterSettings};
+
+    /// Reads the source and returns the messages and the cursor for the next
+    /// run.
+    fn read(files: &[PathBuf], cursor: &Cursor) -> (Vec<String>, Cursor) {
+        let source = CursorSource::new(Box::new(FileSource::new(files.to_vec())), cursor);
+        let next_cursor = source.cursor().clone();
+        let sources: Vec<Box<dyn LogSource>> = vec![Box::new(source)];
+        let (log_lines, _) = read_log_lines(&sources, &LogFilterSettings::default()).unwrap();
+        let messages = log_lines
+            .iter()
+            .map(|log_line| log_line.content().to_string())
+            .collect();
+        (messages, next_cursor)
+    }
+
+    fn line(n: u32) -> String {
+        format!("2022-09-02T13:{:02}:00.00000 kern.info: {}\n", n, n)
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = std::env::temp_dir().join(format!("svlog_cursor_src_{}", std::process::id()));
+        let service = dir.join("kernel");
+        fs::create_dir_all(&service).unwrap();
+        let current = service.join("current");
+        // the last line is still being written
+        fs::write(&current, line(1) + &line(2) + "2022-09-02T13:03").unwrap();
+        let (messages, cursor) = read(std::slice::from_ref(&current), &Cursor::default());
+        assert_eq!(messages, vec!["kern.info: 1", "kern.info: 2"]);
+
+        // svlogd completes the line and rotates "current"
+        let mut content = fs::read_to_string(&current).unwrap();
+        content.push_str(":00.00000 kern.info: 3\n");
+        fs::write(&current, content).unwrap();
+        let rotated = service.join("@400000006311fa3d00000000.s");
+        fs::rename(&current, &rotated).unwrap();
+        fs::write(&current, line(4)).unwrap();
+        let files = [rotated.clone(), current.clone()];
+        let (messages, cursor) = read(&files, &cursor);
+        assert_eq!(messages, vec!["kern.info: 3", "kern.info: 4"]);
+
+        // a processor replaced the rotated file, its lines are new by time
+        fs::copy(&rotated, service.join("tmp")).unwrap();
+        fs::rename(service.join("tmp"), &rotated).unwrap();
+        fs::write(&current, line(4) + &line(5)).unwrap();
+        let (messages, cursor) = read(&files, &cursor);
+        assert_eq!(messages, vec!["kern.info: 5"]);
+        assert!(read(&files, &cursor).0.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
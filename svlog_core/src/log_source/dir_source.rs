@@ -0,0 +1,60 @@
+use std::{io, path::PathBuf};
+
+use svlog_util::{
+    services::{file_paths, select_services, LogPaths},
+    SvLogResult,
+};
+
+use crate::{
+    log_file::service_name,
+    log_source::{LogSource, LogStream},
+};
+
+/// The log files of a socklog directory, e.g. "/var/log/socklog/".
+pub struct DirSource {
+    paths: Vec<PathBuf>,
+    /// Service directories which couldn't be read
+    unreadable: Vec<(PathBuf, io::ErrorKind)>,
+}
+
+impl DirSource {
+    /// Source for the services of the log directory matching the patterns
+    /// (all if empty) and none of the excludes. Patterns are shell-style
+    /// globs like "net*", a leading "^" excludes the matching services. Fails
+    /// if a pattern doesn't match any service or the log directory can't be
+    /// read. Service directories which can't be read are skipped when reading
+    /// the source.
+    pub fn new(log_dir: &str, patterns: &[String], excludes: &[String]) -> SvLogResult<Self> {
+        let services = select_services(log_dir, patterns, excludes)?;
+        let paths = if patterns.is_empty() && excludes.is_empty() {
+            file_paths(log_dir, &[])
+        } else if services.is_empty() {
+            LogPaths::default()
+        } else {
+            file_paths(log_dir, &services)
+        };
+        Ok(DirSource {
+            paths: paths.files,
+            unreadable: paths.unreadable,
+        })
+    }
+}
+
+impl LogSource for DirSource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        Ok(self
+            .paths
+            .iter()
+            .map(|path| LogStream::from_file(service_name(path), path.clone()))
+            .chain(
+                self.unreadable
+                    .iter()
+                    .map(|(path, kind)| LogStream::unreadable(path.clone(), *kind)),
+            )
+            .collect())
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use svlog_util::SvLogResult;
+
+use crate::{
+    log_file::service_name,
+    log_source::{LogSource, LogStream},
+};
+
+/// An explicit list of log files. The service of a file is the name of the
+/// directory containing it.
+pub struct FileSource {
+    paths: Vec<PathBuf>,
+}
+
+impl FileSource {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        FileSource { paths }
+    }
+}
+
+impl LogSource for FileSource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        Ok(self
+            .paths
+            .iter()
+            .map(|path| LogStream::from_file(service_name(path), path.clone()))
+            .collect())
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+}
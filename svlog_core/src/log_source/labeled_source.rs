@@ -0,0 +1,35 @@
+use std::{path::PathBuf, sync::Arc};
+
+use svlog_util::SvLogResult;
+
+use crate::log_source::{LogSource, LogStream};
+
+/// Adds a label to the lines of another source, e.g. to tell apart the logs
+/// of several machines.
+pub struct LabeledSource {
+    label: Arc<str>,
+    source: Box<dyn LogSource>,
+}
+
+impl LabeledSource {
+    pub fn new(label: &str, source: Box<dyn LogSource>) -> Self {
+        LabeledSource {
+            label: Arc::from(label),
+            source,
+        }
+    }
+}
+
+impl LogSource for LabeledSource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        self.source.streams()
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.source.files()
+    }
+
+    fn label(&self) -> Option<Arc<str>> {
+        Some(self.label.clone())
+    }
+}
@@ -0,0 +1,30 @@
+use std::{io::Cursor, sync::Arc};
+
+use svlog_util::SvLogResult;
+
+use crate::log_source::{LogSource, LogStream};
+
+/// Log lines of one service kept in memory, e.g. for tests.
+pub struct MemorySource {
+    service: Arc<str>,
+    content: String,
+}
+
+impl MemorySource {
+    pub fn new(service: &str, content: impl Into<String>) -> Self {
+        MemorySource {
+            service: Arc::from(service),
+            content: content.into(),
+        }
+    }
+}
+
+impl LogSource for MemorySource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        let reader = Cursor::new(self.content.clone().into_bytes());
+        Ok(vec![LogStream::from_reader(
+            self.service.clone(),
+            Box::new(reader),
+        )])
+    }
+}
@@ -0,0 +1,192 @@
+mod archive_source;
+mod cursor_source;
+mod dir_source;
+mod file_source;
+mod labeled_source;
+mod memory_source;
+mod stdin_source;
+
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::Arc,
+};
+
+pub use archive_source::ArchiveSource;
+use chrono::NaiveDateTime;
+pub use cursor_source::CursorSource;
+pub use dir_source::DirSource;
+pub use file_source::FileSource;
+pub use labeled_source::LabeledSource;
+pub use memory_source::MemorySource;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use snafu::ResultExt;
+pub use stdin_source::StdinSource;
+use svlog_util::{lossy_lines, ReadLogDirSnafu, ReadLogFileSnafu, SvLogError, SvLogResult};
+
+use crate::{LogFilterSettings, LogLine};
+
+/// A source of socklog log lines, e.g. a socklog directory or a list of
+/// files. Sources are read by [`read_log_lines`] and merged with each other.
+pub trait LogSource: Send + Sync {
+    /// Returns the streams of the source. Files are only opened when the
+    /// streams are read.
+    fn streams(&self) -> SvLogResult<Vec<LogStream>>;
+
+    /// Returns the files of the source which can be followed for new lines.
+    fn files(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    /// Returns the label shown with the lines of the source, e.g. the name of
+    /// the machine the logs were copied from.
+    fn label(&self) -> Option<Arc<str>> {
+        None
+    }
+}
+
+/// Log lines of one service in the socklog format, e.g. one log file.
+pub struct LogStream {
+    service: Arc<str>,
+    label: Option<Arc<str>>,
+    input: Input,
+    /// Byte range of a file which is read, the whole file if None
+    range: Option<(u64, u64)>,
+    /// Only the lines logged after this time are read
+    after: Option<NaiveDateTime>,
+}
+
+enum Input {
+    File(PathBuf),
+    Reader(Box<dyn BufRead + Send>),
+    Unreadable(PathBuf, io::ErrorKind),
+}
+
+impl LogStream {
+    pub fn from_file(service: Arc<str>, path: PathBuf) -> Self {
+        LogStream {
+            service,
+            label: None,
+            input: Input::File(path),
+            range: None,
+            after: None,
+        }
+    }
+
+    pub fn from_reader(service: Arc<str>, reader: Box<dyn BufRead + Send>) -> Self {
+        LogStream {
+            service,
+            label: None,
+            input: Input::Reader(reader),
+            range: None,
+            after: None,
+        }
+    }
+
+    /// A directory of a source which couldn't be read. Reading the stream
+    /// fails with the given error.
+    pub fn unreadable(path: PathBuf, kind: io::ErrorKind) -> Self {
+        LogStream {
+            service: Arc::from(""),
+            label: None,
+            input: Input::Unreadable(path, kind),
+            range: None,
+            after: None,
+        }
+    }
+
+    /// Reads the lines matching the filters. Lines which aren't in the socklog
+    /// format are skipped.
+    fn read(self, log_settings: &LogFilterSettings) -> SvLogResult<Vec<LogLine>> {
+        let reader: Box<dyn BufRead + Send> = match self.input {
+            Input::File(path) => {
+                let context = ReadLogFileSnafu {
+                    path: path.display().to_string(),
+                };
+                let mut file = File::open(&path).context(context.clone())?;
+                match self.range {
+                    Some((start, end)) => {
+                        file.seek(SeekFrom::Start(start)).context(context)?;
+                        Box::new(BufReader::new(file.take(end.saturating_sub(start))))
+                    }
+                    None => Box::new(BufReader::new(file)),
+                }
+            }
+            Input::Reader(reader) => reader,
+            Input::Unreadable(path, kind) => {
+                return Err(io::Error::from(kind)).context(ReadLogDirSnafu {
+                    path: path.display().to_string(),
+                })
+            }
+        };
+        Ok(lossy_lines(reader)
+            .filter_map(|l| LogLine::new(l).ok())
+            .filter(|l| !matches!(self.after, Some(after) if *l.date() <= after))
+            .filter(|l| log_settings.matches(l))
+            .map(|l| {
+                l.with_service(self.service.clone())
+                    .with_label(self.label.clone())
+            })
+            .collect())
+    }
+}
+
+/// Reads the matching lines of all sources in parallel and merges them in
+/// chronological order. Streams which can't be read (e.g. a file without read
+/// permission or removed in the meantime) are skipped, their errors are
+/// returned along with the lines.
+pub fn read_log_lines(
+    sources: &[Box<dyn LogSource>],
+    log_settings: &LogFilterSettings,
+) -> SvLogResult<(BTreeSet<LogLine>, Vec<SvLogError>)> {
+    let mut streams = Vec::new();
+    for source in sources {
+        let label = source.label();
+        for mut stream in source.streams()? {
+            stream.label = label.clone();
+            streams.push(stream);
+        }
+    }
+    let results: Vec<SvLogResult<Vec<LogLine>>> = streams
+        .into_par_iter()
+        .map(|stream| stream.read(log_settings))
+        .collect();
+    let mut log_lines = BTreeSet::new();
+    let mut skipped = Vec::new();
+    for result in results {
+        match result {
+            Ok(lines) => log_lines.extend(lines),
+            Err(e) => skipped.push(e),
+        }
+    }
+    Ok((log_lines, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileSource, MemorySource};
+
+    #[test]
+    fn skip_unreadable_files() {
+        let sources: Vec<Box<dyn LogSource>> = vec![
+            Box::new(FileSource::new(vec![PathBuf::from(
+                "/nonexistent/kernel/current",
+            )])),
+            Box::new(MemorySource::new(
+                "daemon",
+                "2022-09-01T13:33:00.00000 daemon.info: d",
+            )),
+        ];
+        let (log_lines, skipped) = read_log_lines(&sources, &LogFilterSettings::default()).unwrap();
+        assert_eq!(log_lines.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert!(matches!(
+            &skipped[0],
+            SvLogError::ReadLogFileError { path, .. } if path == "/nonexistent/kernel/current"
+        ));
+        assert!(!skipped[0].is_permission_denied());
+    }
+}
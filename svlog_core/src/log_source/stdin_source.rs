@@ -0,0 +1,30 @@
+use std::{
+    io::{stdin, BufReader},
+    sync::Arc,
+};
+
+use svlog_util::SvLogResult;
+
+use crate::log_source::{LogSource, LogStream};
+
+/// Log lines read from the standard input. It can only be read once.
+pub struct StdinSource {
+    service: Arc<str>,
+}
+
+impl StdinSource {
+    pub fn new(service: &str) -> Self {
+        StdinSource {
+            service: Arc::from(service),
+        }
+    }
+}
+
+impl LogSource for StdinSource {
+    fn streams(&self) -> SvLogResult<Vec<LogStream>> {
+        Ok(vec![LogStream::from_reader(
+            self.service.clone(),
+            Box::new(BufReader::new(stdin())),
+        )])
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use chrono::Timelike;
+use serde_json::{json, Value};
+
+use crate::{Entry, LogLine};
+
+/// Builds the body of a request to the push API of Grafana Loki
+/// ("/loki/api/v1/push"). The lines are grouped into streams by their labels
+/// "service", "facility" (if the line has one), "priority" and "host", the
+/// facility and priority are removed from the lines.
+pub fn loki_push_body(log_lines: &[LogLine], hostname: &str) -> Value {
+    let mut streams: BTreeMap<BTreeMap<&str, String>, Vec<Value>> = BTreeMap::new();
+    for log_line in log_lines {
+        let entry = Entry::new(log_line);
+        let mut labels = BTreeMap::new();
+        labels.insert("service", entry.service);
+        if let Some(facility) = entry.facility {
+            labels.insert("facility", facility);
+        }
+        labels.insert("priority", entry.priority.name().to_string());
+        labels.insert("host", hostname.to_string());
+        streams
+            .entry(labels)
+            .or_default()
+            .push(json!([timestamp_nanos(log_line), entry.message]));
+    }
+    let streams: Vec<Value> = streams
+        .into_iter()
+        .map(|(labels, values)| json!({ "stream": labels, "values": values }))
+        .collect();
+    json!({ "streams": streams })
+}
+
+/// Returns the time of the log line in nanoseconds since the epoch as string.
+fn timestamp_nanos(log_line: &LogLine) -> String {
+    let date = log_line.date();
+    format!("{}{:0>9}", date.and_utc().timestamp(), date.nanosecond())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn line(service: &str, line: &str) -> LogLine {
+        LogLine::new(line.to_string())
+            .unwrap()
+            .with_service(Arc::from(service))
+    }
+
+    #[test]
+    fn push_body() {
+        let log_lines = [
+            line("kernel", "2022-09-02T13:32:53.68972 kern.err: a"),
+            line("daemon", "2022-09-02T13:33:53.00001 daemon.info: b"),
+            line("kernel", "2022-09-02T13:34:53.00000 kern.err: c"),
+            line("tty1", "2022-09-02T13:35:53.00000 plain output"),
+        ];
+        assert_eq!(
+            loki_push_body(&log_lines, "void"),
+            json!({
+                "streams": [
+                    {
+                        "stream": {"facility": "daemon", "host": "void", "priority": "info", "service": "daemon"},
+                        "values": [["1662125633000010000", "b"]]
+                    },
+                    {
+                        "stream": {"facility": "kern", "host": "void", "priority": "err", "service": "kernel"},
+                        "values": [["1662125573689720000", "a"], ["1662125693000000000", "c"]]
+                    },
+                    {
+                        "stream": {"host": "void", "priority": "debug", "service": "tty1"},
+                        "values": [["1662125753000000000", "plain output"]]
+                    }
+                ]
+            })
+        );
+    }
+}
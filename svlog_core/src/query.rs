@@ -0,0 +1,373 @@
+use chrono::NaiveDateTime;
+use regex::Regex;
+use svlog_util::{regex::build_regex, LogPriority, SvLogError, SvLogResult, TimeWindow};
+
+use crate::{read_log_lines, DirSource, Entry, LogFilterSettings, LogSource};
+
+static DEFAULT_LOG_DIR: &str = "/var/log/socklog/";
+
+/// Builder for queries of socklog log files.
+///
+/// All filters are optional. Without any filter, all lines of all services
+/// in "/var/log/socklog/" are returned. Besides log directories, any
+/// [`LogSource`] can be queried.
+///
+/// ```no_run
+/// use chrono::NaiveDate;
+/// use svlog_core::{LogPriority, Query};
+///
+/// let since = NaiveDate::from_ymd_opt(2022, 9, 1)
+///     .unwrap()
+///     .and_hms_opt(0, 0, 0)
+///     .unwrap();
+/// let (entries, skipped) = Query::new()
+///     .service("kernel")
+///     .since(since)
+///     .priorities(LogPriority::min(), LogPriority::parse("warn").unwrap())
+///     .pattern("usb")
+///     .entries()
+///     .unwrap();
+/// for entry in entries {
+///     println!("{} {}", entry.timestamp, entry.message);
+/// }
+/// for error in skipped {
+///     eprintln!("skipped: {error}");
+/// }
+/// ```
+pub struct Query {
+    log_dirs: Vec<String>,
+    sources: Vec<Box<dyn LogSource>>,
+    services: Vec<String>,
+    excluded_services: Vec<String>,
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    windows: Vec<TimeWindow>,
+    min_priority: LogPriority,
+    max_priority: LogPriority,
+    patterns: Vec<String>,
+    case_insensitive: bool,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query {
+            log_dirs: Vec::new(),
+            sources: Vec::new(),
+            services: Vec::new(),
+            excluded_services: Vec::new(),
+            since: None,
+            until: None,
+            windows: Vec::new(),
+            min_priority: LogPriority::min(),
+            max_priority: LogPriority::max(),
+            patterns: Vec::new(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Adds a socklog directory to read. Defaults to "/var/log/socklog/".
+    pub fn log_dir(mut self, log_dir: impl Into<String>) -> Self {
+        let mut log_dir = log_dir.into();
+        if !log_dir.ends_with('/') {
+            log_dir.push('/');
+        }
+        self.log_dirs.push(log_dir);
+        self
+    }
+
+    /// Adds a source to read, e.g. a [`FileSource`](crate::FileSource).
+    /// The services and the default log directory only apply to log
+    /// directories.
+    pub fn source(mut self, source: impl LogSource + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Adds a service (i.e. a subdirectory of the log directories) to read.
+    /// Defaults to all services. The service may be a shell-style pattern
+    /// (e.g. "net*"), a leading "^" excludes the matching services instead.
+    pub fn service(mut self, service: impl Into<String>) -> Self {
+        self.services.push(service.into());
+        self
+    }
+
+    /// Excludes the services matching the shell-style pattern.
+    pub fn exclude_service(mut self, service: impl Into<String>) -> Self {
+        self.excluded_services.push(service.into());
+        self
+    }
+
+    /// Only returns entries at or after this time (UTC).
+    pub fn since(mut self, since: NaiveDateTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only returns entries at or before this time (UTC).
+    pub fn until(mut self, until: NaiveDateTime) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Adds a time window (e.g. a boot) to read. If windows are added, only
+    /// entries in any of them are returned.
+    pub fn window(mut self, window: TimeWindow) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Only returns entries with a priority between min and max (both
+    /// included). Note that 0 ("emerg") is the most important priority.
+    pub fn priorities(mut self, min: LogPriority, max: LogPriority) -> Self {
+        self.min_priority = min;
+        self.max_priority = max;
+        self
+    }
+
+    /// Adds a regular expression the entries have to match. If several
+    /// patterns are added, entries matching any of them are returned.
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    /// Matches the patterns case insensitively.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Reads the log files and returns the matching entries in chronological
+    /// order. Fails if a service doesn't exist or a pattern is invalid.
+    /// Log files which can't be read (e.g. because of missing permissions)
+    /// are skipped, their errors are returned along with the entries.
+    pub fn entries(&self) -> SvLogResult<(impl Iterator<Item = Entry>, Vec<SvLogError>)> {
+        let log_filter_settings = self.log_filter_settings()?;
+        let log_dirs = if self.log_dirs.is_empty() && self.sources.is_empty() {
+            vec![DEFAULT_LOG_DIR.to_string()]
+        } else {
+            self.log_dirs.clone()
+        };
+        let mut dir_sources: Vec<Box<dyn LogSource>> = Vec::new();
+        for log_dir in &log_dirs {
+            dir_sources.push(Box::new(DirSource::new(
+                log_dir,
+                &self.services,
+                &self.excluded_services,
+            )?));
+        }
+        let (mut log_lines, mut skipped) = read_log_lines(&dir_sources, &log_filter_settings)?;
+        let (mut source_lines, mut source_skipped) =
+            read_log_lines(&self.sources, &log_filter_settings)?;
+        log_lines.append(&mut source_lines);
+        skipped.append(&mut source_skipped);
+        Ok((
+            log_lines.into_iter().map(|log_line| Entry::new(&log_line)),
+            skipped,
+        ))
+    }
+
+    fn log_filter_settings(&self) -> SvLogResult<LogFilterSettings> {
+        let time_window = TimeWindow::new(self.since, self.until);
+        let windows = if self.windows.is_empty() {
+            vec![time_window]
+        } else {
+            self.windows
+                .iter()
+                .filter_map(|window| window.intersect(&time_window))
+                .collect()
+        };
+        Ok(LogFilterSettings {
+            re: self.regex()?,
+            windows,
+            min_priority: self.min_priority,
+            max_priority: self.max_priority,
+        })
+    }
+
+    /// Combines all patterns into one regex. The patterns are compiled on their
+    /// own first so that errors refer to the pattern given by the caller.
+    fn regex(&self) -> SvLogResult<Option<Regex>> {
+        if self.patterns.is_empty() {
+            return Ok(None);
+        }
+        for pattern in &self.patterns {
+            self.build_regex(pattern)?;
+        }
+        let combined: Vec<String> = self
+            .patterns
+            .iter()
+            .map(|pattern| format!("(?:{pattern})"))
+            .collect();
+        self.build_regex(&combined.join("|")).map(Some)
+    }
+
+    fn build_regex(&self, pattern: &str) -> SvLogResult<Regex> {
+        build_regex(pattern, self.case_insensitive)
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{FileSource, LabeledSource, MemorySource};
+
+    static LOG_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/socklog/");
+
+    fn ndt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn messages(query: &Query) -> Vec<String> {
+        query
+            .entries()
+            .unwrap()
+            .0
+            .map(|entry| entry.message)
+            .collect()
+    }
+
+    #[test]
+    fn all_entries_merged() {
+        let entries: Vec<Entry> = Query::new().log_dir(LOG_DIR).entries().unwrap().0.collect();
+        assert_eq!(entries.len(), 16);
+        assert!(entries.windows(2).all(|e| e[0].timestamp <= e[1].timestamp));
+        assert_eq!(entries[0].service, "daemon");
+        assert_eq!(entries[0].facility.as_deref(), Some("deamon"));
+        assert_eq!(entries[0].message, "d7");
+        assert_eq!(entries[15].service, "kernel");
+    }
+
+    #[test]
+    fn services() {
+        let query = Query::new().log_dir(LOG_DIR).service("kernel");
+        assert!(query
+            .entries()
+            .unwrap()
+            .0
+            .all(|entry| entry.service == "kernel"));
+        let query = Query::new().log_dir(LOG_DIR).service("nope");
+        assert!(query.entries().is_err());
+    }
+
+    #[test]
+    fn service_patterns() {
+        let query = Query::new().log_dir(LOG_DIR).service("k*");
+        assert!(query
+            .entries()
+            .unwrap()
+            .0
+            .all(|entry| entry.service == "kernel"));
+        let query = Query::new().log_dir(LOG_DIR).service("^kernel");
+        assert!(query
+            .entries()
+            .unwrap()
+            .0
+            .all(|entry| entry.service == "daemon"));
+        let query = Query::new()
+            .log_dir(LOG_DIR)
+            .exclude_service("kernel")
+            .exclude_service("d*");
+        assert_eq!(query.entries().unwrap().0.count(), 0);
+    }
+
+    #[test]
+    fn time_window() {
+        let query = Query::new()
+            .log_dir(LOG_DIR)
+            .since(ndt("2022-09-01 13:35:00"))
+            .until(ndt("2022-09-01 13:38:00"));
+        assert_eq!(messages(&query), vec!["d4", "d3", "d2"]);
+    }
+
+    #[test]
+    fn windows() {
+        let query = Query::new()
+            .log_dir(LOG_DIR)
+            .window(TimeWindow::new(None, Some(ndt("2022-09-01 13:33:00"))))
+            .window(TimeWindow::new(Some(ndt("2022-09-01 13:39:00")), None))
+            .until(ndt("2022-09-01 13:40:00"));
+        assert_eq!(messages(&query), vec!["d7", "d0"]);
+    }
+
+    #[test]
+    fn priorities() {
+        let query = Query::new().log_dir(LOG_DIR).service("daemon").priorities(
+            LogPriority::parse("crit").unwrap(),
+            LogPriority::parse("err").unwrap(),
+        );
+        assert_eq!(messages(&query), vec!["d3", "d2"]);
+    }
+
+    #[test]
+    fn patterns() {
+        let query = Query::new()
+            .log_dir(LOG_DIR)
+            .pattern(": d[12]$")
+            .pattern("HELLO")
+            .case_insensitive(true);
+        assert_eq!(messages(&query), vec!["d2", "d1", "hello fren!"]);
+    }
+
+    #[test]
+    fn sources() {
+        let query = Query::new()
+            .source(MemorySource::new(
+                "test",
+                "2022-09-01T13:33:00.00000 user.warn: m2\n\
+                 invalid line\n\
+                 2022-09-01T13:32:00.00000 user.info: m1\n",
+            ))
+            .source(FileSource::new(vec![
+                PathBuf::from(LOG_DIR).join("kernel/current")
+            ]));
+        let entries: Vec<Entry> = query.entries().unwrap().0.collect();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0].message, "m1");
+        assert_eq!(entries[0].service, "test");
+        assert_eq!(entries[1].message, "m2");
+        assert_eq!(entries[2].service, "kernel");
+    }
+
+    #[test]
+    fn skipped_files() {
+        let query = Query::new()
+            .source(MemorySource::new(
+                "test",
+                "2022-09-01T13:32:00.00000 user.info: m1",
+            ))
+            .source(FileSource::new(vec![PathBuf::from(
+                "/nonexistent/kernel/current",
+            )]));
+        let (entries, skipped) = query.entries().unwrap();
+        assert_eq!(entries.count(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert!(matches!(
+            &skipped[0],
+            SvLogError::ReadLogFileError { path, .. } if path == "/nonexistent/kernel/current"
+        ));
+    }
+
+    #[test]
+    fn labeled_source() {
+        let source = MemorySource::new("test", "2022-09-01T13:32:00.00000 user.info: m1");
+        let query = Query::new().source(LabeledSource::new("host", Box::new(source)));
+        let entries: Vec<Entry> = query.entries().unwrap().0.collect();
+        assert_eq!(entries[0].label.as_deref(), Some("host"));
+        assert_eq!(entries[0].service, "test");
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let query = Query::new().log_dir(LOG_DIR).pattern("(");
+        assert!(query.entries().is_err());
+    }
+}
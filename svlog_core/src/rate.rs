@@ -0,0 +1,241 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Instant,
+};
+
+use chrono::{NaiveDateTime, Timelike};
+use svlog_util::RateRule;
+
+use crate::LogLine;
+
+/// Source of the current time of a [`RateMonitor`], replaced in tests.
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+}
+
+/// The monotonic system clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A service which exceeded the limit of a [`RateRule`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateAlert {
+    /// Index of the rule in the rules of the monitor.
+    pub rule: usize,
+    pub service: String,
+    /// Number of lines within the window.
+    pub count: usize,
+    /// Time of the line which exceeded the limit.
+    pub timestamp: NaiveDateTime,
+}
+
+impl RateAlert {
+    /// Returns the line reporting the alert, e.g. "svlog.alert: rate limit
+    /// exceeded by "kernel": 21 entries with priority 0..3 within 60s (limit
+    /// 20)", with the time of the line which exceeded the limit and the
+    /// service of the alert.
+    pub fn log_line(&self, rule: &RateRule) -> LogLine {
+        let line = format!(
+            "{}.{:0>5} svlog.alert: rate limit exceeded by \"{}\": {} entries with priority \
+            {}..{} within {}s (limit {})",
+            self.timestamp.format("%Y-%m-%dT%H:%M:%S"),
+            self.timestamp.nanosecond() / 10_000,
+            self.service,
+            self.count,
+            rule.min_priority.value(),
+            rule.max_priority.value(),
+            rule.window.as_secs(),
+            rule.limit
+        );
+        LogLine::new(line)
+            .expect("alert line is valid")
+            .with_service(Arc::from(self.service.as_str()))
+    }
+}
+
+/// Counts new log lines per rule and service within the sliding windows of
+/// the rules. The lines are counted at the time they are recorded, not at the
+/// time they were logged.
+pub struct RateMonitor<C: Clock = SystemClock> {
+    rules: Vec<RateRule>,
+    clock: C,
+    /// Times of the lines within the window per rule and service and whether
+    /// the limit is exceeded
+    counters: Vec<HashMap<String, (VecDeque<Instant>, bool)>>,
+}
+
+impl RateMonitor {
+    pub fn new(rules: Vec<RateRule>) -> Self {
+        RateMonitor::with_clock(rules, SystemClock)
+    }
+}
+
+impl Default for RateMonitor {
+    fn default() -> Self {
+        RateMonitor::new(Vec::new())
+    }
+}
+
+impl<C: Clock> RateMonitor<C> {
+    pub fn with_clock(rules: Vec<RateRule>, clock: C) -> Self {
+        let counters = rules.iter().map(|_| HashMap::new()).collect();
+        RateMonitor {
+            rules,
+            clock,
+            counters,
+        }
+    }
+
+    pub fn rules(&self) -> &[RateRule] {
+        &self.rules
+    }
+
+    /// Records a new log line and returns an alert for every rule whose limit
+    /// the service exceeds now. A service is reported once until its count
+    /// drops to the limit again.
+    pub fn record(&mut self, log_line: &LogLine) -> Vec<RateAlert> {
+        let now = self.clock.now();
+        let mut alerts = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.applies_to(log_line.service(), log_line.priority()) {
+                continue;
+            }
+            let (times, exceeded) = self.counters[i]
+                .entry(log_line.service().to_string())
+                .or_default();
+            times.push_back(now);
+            while matches!(times.front(), Some(time) if now.duration_since(*time) >= rule.window) {
+                times.pop_front();
+            }
+            if times.len() <= rule.limit {
+                *exceeded = false;
+            } else if !*exceeded {
+                *exceeded = true;
+                alerts.push(RateAlert {
+                    rule: i,
+                    service: log_line.service().to_string(),
+                    count: times.len(),
+                    timestamp: *log_line.date(),
+                });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use svlog_util::LogPriority;
+
+    use super::*;
+
+    /// Clock which only advances when told to.
+    #[derive(Clone)]
+    struct FakeClock {
+        now: Arc<Mutex<Instant>>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            FakeClock {
+                now: Arc::new(Mutex::new(Instant::now())),
+            }
+        }
+
+        fn advance(&self, seconds: u64) {
+            *self.now.lock().unwrap() += Duration::from_secs(seconds);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    fn line(service: &str, content: &str) -> LogLine {
+        LogLine::new(format!("2022-09-02T13:40:00.00000 {content}"))
+            .unwrap()
+            .with_service(Arc::from(service))
+    }
+
+    #[test]
+    fn sliding_window() {
+        let clock = FakeClock::new();
+        let rule = RateRule::parse("kernel:..err:2/1m").unwrap();
+        let mut monitor = RateMonitor::with_clock(vec![rule], clock.clone());
+        let err = line("kernel", "kern.err: x");
+        assert!(monitor.record(&err).is_empty());
+        clock.advance(30);
+        assert!(monitor.record(&err).is_empty());
+        // the first line dropped out of the window
+        clock.advance(31);
+        assert!(monitor.record(&err).is_empty());
+        clock.advance(1);
+        let alerts = monitor.record(&err);
+        assert_eq!(
+            alerts,
+            vec![RateAlert {
+                rule: 0,
+                service: "kernel".to_string(),
+                count: 3,
+                timestamp: *err.date(),
+            }]
+        );
+    }
+
+    #[test]
+    fn alert_once_per_burst() {
+        let clock = FakeClock::new();
+        let rule = RateRule::parse("1/10s").unwrap();
+        let mut monitor = RateMonitor::with_clock(vec![rule], clock.clone());
+        let info = line("daemon", "daemon.info: x");
+        assert!(monitor.record(&info).is_empty());
+        assert_eq!(monitor.record(&info).len(), 1);
+        assert!(monitor.record(&info).is_empty());
+        clock.advance(10);
+        assert!(monitor.record(&info).is_empty());
+        assert_eq!(monitor.record(&info)[0].count, 2);
+    }
+
+    #[test]
+    fn services_and_priorities() {
+        let clock = FakeClock::new();
+        let rule = RateRule::parse("k*:err:1/1m").unwrap();
+        let mut monitor = RateMonitor::with_clock(vec![rule], clock);
+        assert!(monitor.record(&line("kernel", "kern.err: x")).is_empty());
+        assert!(monitor.record(&line("kernel", "kern.info: x")).is_empty());
+        assert!(monitor.record(&line("daemon", "daemon.err: x")).is_empty());
+        assert!(monitor.record(&line("kmsg", "kern.err: x")).is_empty());
+        assert_eq!(monitor.record(&line("kernel", "kern.err: x")).len(), 1);
+    }
+
+    #[test]
+    fn alert_line() {
+        let rule = RateRule::parse("kernel:..err:1/1m").unwrap();
+        let log_line = LogLine::new("2022-09-02T13:40:02.01230 kern.crit: x".to_string()).unwrap();
+        let alert = RateAlert {
+            rule: 0,
+            service: "kernel".to_string(),
+            count: 2,
+            timestamp: *log_line.date(),
+        };
+        let alert_line = alert.log_line(&rule);
+        assert_eq!(
+            alert_line.to_string(),
+            "2022-09-02T13:40:02.01230 svlog.alert: rate limit exceeded by \"kernel\": \
+            2 entries with priority 0..3 within 60s (limit 1)"
+        );
+        assert_eq!(alert_line.date(), log_line.date());
+        assert_eq!(alert_line.service(), "kernel");
+        assert_eq!(alert_line.priority(), &LogPriority::parse("alert").unwrap());
+    }
+}
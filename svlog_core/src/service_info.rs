@@ -0,0 +1,148 @@
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use chrono::NaiveDateTime;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use svlog_util::{
+    lossy_lines,
+    services::{all_services, file_paths},
+    SvLogResult,
+};
+
+use crate::LogLine;
+
+/// Number of bytes read from the end of a log file to find its newest entry
+static TAIL_SIZE: u64 = 64 * 1024;
+/// Number of lines read from the start of a log file to find its oldest entry
+static HEAD_LINES: usize = 16;
+
+/// Summary of the log files of a service.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ServiceInfo {
+    pub name: String,
+    /// Number of log files ("current", "*.s" and "*.u").
+    pub files: usize,
+    /// Total size of the log files in bytes.
+    pub size: u64,
+    /// Number of lines in "current".
+    pub current_lines: usize,
+    /// Time of the oldest entry in UTC.
+    pub oldest: Option<NaiveDateTime>,
+    /// Time of the newest entry in UTC.
+    pub newest: Option<NaiveDateTime>,
+    /// Whether there are "*.u" files, i.e. files which weren't processed by
+    /// the svlogd processor.
+    pub unprocessed: bool,
+}
+
+/// Returns the summaries of all services in the log directory ordered by
+/// name. Files which can't be read are ignored, fails if the log directory
+/// can't be read.
+pub fn service_infos(log_dir: &str) -> SvLogResult<Vec<ServiceInfo>> {
+    let mut services = all_services(log_dir)?;
+    services.sort();
+    Ok(services
+        .into_par_iter()
+        .map(|service| service_info(log_dir, service))
+        .collect())
+}
+
+fn service_info(log_dir: &str, name: String) -> ServiceInfo {
+    let paths = file_paths(log_dir, std::slice::from_ref(&name)).files;
+    let mut info = ServiceInfo {
+        name,
+        files: paths.len(),
+        size: 0,
+        current_lines: 0,
+        oldest: None,
+        newest: None,
+        unprocessed: false,
+    };
+    for path in &paths {
+        info.size += fs::metadata(path).map_or(0, |meta| meta.len());
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        if file_name == Some("current") {
+            info.current_lines = count_lines(path);
+        }
+        if matches!(file_name, Some(name) if name.ends_with(".u")) {
+            info.unprocessed = true;
+        }
+        info.oldest = match (info.oldest, first_date(path)) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        info.newest = info.newest.max(last_date(path));
+    }
+    info
+}
+
+fn count_lines(path: &Path) -> usize {
+    File::open(path).map_or(0, |file| BufReader::new(file).lines().count())
+}
+
+/// Returns the time of the first entry in the log file.
+fn first_date(path: &Path) -> Option<NaiveDateTime> {
+    let file = File::open(path).ok()?;
+    lossy_lines(BufReader::new(file))
+        .take(HEAD_LINES)
+        .find_map(|line| LogLine::new(line).ok())
+        .map(|log_line| *log_line.date())
+}
+
+/// Returns the time of the last entry in the log file. Only the end of the file
+/// is read.
+fn last_date(path: &Path) -> Option<NaiveDateTime> {
+    let mut file = File::open(path).ok()?;
+    let length = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(length.saturating_sub(TAIL_SIZE)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .find_map(|line| LogLine::new(line.to_string()).ok())
+        .map(|log_line| *log_line.date())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LOG_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/socklog/");
+
+    fn ndt(s: &str) -> Option<NaiveDateTime> {
+        Some(NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap())
+    }
+
+    #[test]
+    fn infos() {
+        let infos = service_infos(LOG_DIR).unwrap();
+        assert_eq!(
+            infos,
+            vec![
+                ServiceInfo {
+                    name: "daemon".to_string(),
+                    files: 1,
+                    size: 340,
+                    current_lines: 0,
+                    oldest: ndt("2022-09-01 13:32:53.68972"),
+                    newest: ndt("2022-09-01 13:39:53.68972"),
+                    unprocessed: true,
+                },
+                ServiceInfo {
+                    name: "kernel".to_string(),
+                    files: 1,
+                    size: 477,
+                    current_lines: 8,
+                    oldest: ndt("2022-09-02 13:32:53.68972"),
+                    newest: ndt("2022-09-02 13:39:53.68972"),
+                    unprocessed: false,
+                },
+            ]
+        );
+    }
+}
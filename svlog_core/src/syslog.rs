@@ -0,0 +1,149 @@
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use svlog_util::SyslogFormat;
+
+use crate::{Entry, LogLine};
+
+/// Facility "user" and severity "notice" which RFC 3164 assigns to messages
+/// without priority
+static DEFAULT_FACILITY: u8 = 1;
+static DEFAULT_SEVERITY: u8 = 5;
+
+/// Names of the facilities as socklog writes them, by their code
+static FACILITIES: [&str; 24] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv",
+    "ftp", "ntp", "security", "console", "cron2", "local0", "local1", "local2", "local3", "local4",
+    "local5", "local6", "local7",
+];
+
+/// Formats a log line as syslog message for forwarding it to a syslog server.
+/// The facility and priority of socklog become the PRI, the service the
+/// APP-NAME (RFC 5424) or TAG (RFC 3164). RFC 3164 timestamps have no
+/// timezone, they are given in `tz` (UTC if `None`).
+pub fn format_syslog(
+    log_line: &LogLine,
+    format: SyslogFormat,
+    hostname: &str,
+    tz: &Option<Tz>,
+) -> String {
+    let entry = Entry::new(log_line);
+    let pri = match &entry.facility {
+        Some(facility) => {
+            let code = FACILITIES
+                .iter()
+                .position(|name| name == facility)
+                .map_or(DEFAULT_FACILITY, |code| code as u8);
+            code * 8 + entry.priority.value()
+        }
+        // NOTE: lines without "facility.priority" prefix have no priority
+        None if entry.message == log_line.content() => DEFAULT_FACILITY * 8 + DEFAULT_SEVERITY,
+        None => DEFAULT_FACILITY * 8 + entry.priority.value(),
+    };
+    match format {
+        SyslogFormat::Rfc5424 => format!(
+            "<{}>1 {} {} {} - - - {}",
+            pri,
+            log_line.format_date(&None),
+            header_field(hostname, 255),
+            header_field(&entry.service, 48),
+            entry.message
+        ),
+        SyslogFormat::Rfc3164 => {
+            let timestamp = match tz {
+                Some(tz) => tz
+                    .from_utc_datetime(&entry.timestamp)
+                    .format("%b %e %H:%M:%S")
+                    .to_string(),
+                None => entry.timestamp.format("%b %e %H:%M:%S").to_string(),
+            };
+            let tag: String = entry
+                .service
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+                .take(32)
+                .collect();
+            format!(
+                "<{}>{} {} {}: {}",
+                pri,
+                timestamp,
+                header_field(hostname, 255),
+                if tag.is_empty() { "svlog" } else { &tag },
+                entry.message
+            )
+        }
+    }
+}
+
+/// Returns the value for a header field of RFC 5424 which only allows printable
+/// ASCII characters without spaces and uses "-" for empty values.
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn line(service: &str, content: &str) -> LogLine {
+        LogLine::new(format!("2022-09-02T13:40:00.12345 {content}"))
+            .unwrap()
+            .with_service(Arc::from(service))
+    }
+
+    #[test]
+    fn rfc5424() {
+        let log_line = line("kernel", "kern.err: usb 1-1: device not accepting address");
+        assert_eq!(
+            format_syslog(&log_line, SyslogFormat::Rfc5424, "void", &None),
+            "<3>1 2022-09-02T13:40:00.12345Z void kernel - - - \
+            usb 1-1: device not accepting address"
+        );
+        let log_line = line("sshd", "authpriv.info: Accepted publickey");
+        assert_eq!(
+            format_syslog(&log_line, SyslogFormat::Rfc5424, "my host", &None),
+            "<86>1 2022-09-02T13:40:00.12345Z my_host sshd - - - Accepted publickey"
+        );
+    }
+
+    #[test]
+    fn rfc3164() {
+        let log_line = line("daemon", "daemon.warn: low battery");
+        assert_eq!(
+            format_syslog(&log_line, SyslogFormat::Rfc3164, "void", &None),
+            "<28>Sep  2 13:40:00 void daemon: low battery"
+        );
+        assert_eq!(
+            format_syslog(
+                &log_line,
+                SyslogFormat::Rfc3164,
+                "void",
+                &Some(chrono_tz::Europe::Zurich)
+            ),
+            "<28>Sep  2 15:40:00 void daemon: low battery"
+        );
+    }
+
+    #[test]
+    fn priorities() {
+        let format =
+            |content: &str| format_syslog(&line("x", content), SyslogFormat::Rfc3164, "", &None);
+        // no prefix: user.notice
+        assert!(format("plain output").starts_with("<13>"));
+        assert!(format("local7.debug: x").starts_with("<191>"));
+        // unknown facility: user
+        assert!(format("foo.crit: x").starts_with("<10>"));
+        assert!(format("kern.emerg: x").starts_with("<0>"));
+        assert!(format("x").ends_with(" - x: x"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::{fs::File, io::BufReader, ops::Sub};
+
+use chrono::{Duration, NaiveDateTime};
+use snafu::ResultExt;
+
+use crate::{
+    lines::lossy_lines,
+    os_times::Boot,
+    regex::RE_KERNEL_UPTIME,
+    services::file_paths,
+    svlog_error::{OpenFileSnafu, SvLogResult},
+};
+
+// Detects boots from the logs of socklog's kernel service. A boot starts with
+// the "Linux version" banner or when the kernel's uptime (e.g. the
+// "[   12.345678]" prefix) jumps backwards. The start of a boot is estimated
+// from the log timestamps and uptimes, as socklog only reads the kernel
+// messages of the early boot phase once it is running itself.
+
+static KERNEL_SERVICE: &str = "kernel";
+// NOTE: "%.f" reads the 5 digits of socklog as fraction of a second, like the
+// digits of the uptime
+static DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+static BOOT_BANNER: &str = "Linux version ";
+
+// Reads all boots from the kernel logs in log_dir, ordered from the last boot
+// to the first one. Every boot but the last one ends one second before the
+// next boot.
+pub fn read_kernel_boots(log_dir: &str) -> SvLogResult<Vec<Boot>> {
+    let mut lines: Vec<(NaiveDateTime, String)> = Vec::new();
+    for path in file_paths(log_dir, &[KERNEL_SERVICE.to_string()]).files {
+        let file = File::open(&path).context(OpenFileSnafu {
+            path: format!("{:?}", path),
+        })?;
+        lines.extend(lossy_lines(BufReader::new(file)).filter_map(|line| parse_line(&line)));
+    }
+    // NOTE: the order of lines with the same timestamp has to be kept
+    lines.sort_by_key(|(date, _)| *date);
+    Ok(parse_kernel_boots(&lines))
+}
+
+fn parse_line(line: &str) -> Option<(NaiveDateTime, String)> {
+    let date = NaiveDateTime::parse_from_str(line.get(..25)?, DATE_FORMAT).ok()?;
+    Some((date, line[25..].trim().to_string()))
+}
+
+fn uptime(content: &str) -> Option<Duration> {
+    let captures = RE_KERNEL_UPTIME.captures(content)?;
+    let seconds: i64 = captures[1].parse().ok()?;
+    let fraction = format!("{:0<9}", &captures[2]);
+    let nanoseconds: i64 = fraction.parse().ok()?;
+    Some(Duration::seconds(seconds) + Duration::nanoseconds(nanoseconds))
+}
+
+fn parse_kernel_boots(lines: &[(NaiveDateTime, String)]) -> Vec<Boot> {
+    let mut boots: Vec<Boot> = Vec::new();
+    let mut last_uptime: Option<Duration> = None;
+    for (date, content) in lines {
+        let uptime = uptime(content);
+        let start = date.sub(uptime.unwrap_or_else(Duration::zero));
+        let is_new_boot = boots.is_empty()
+            || content.contains(BOOT_BANNER)
+            || matches!(
+                (uptime, last_uptime),
+                (Some(uptime), Some(last_uptime)) if uptime + Duration::seconds(1) < last_uptime
+            );
+        if is_new_boot {
+            boots.push(Boot { start, end: None });
+        } else if let Some(current) = boots.last_mut() {
+            current.start = current.start.min(start);
+        }
+        last_uptime = uptime.or(last_uptime);
+    }
+    for i in 1..boots.len() {
+        boots[i - 1].end = Some(boots[i].start.sub(Duration::seconds(1)));
+    }
+    boots.reverse();
+    boots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<(NaiveDateTime, String)> {
+        lines.iter().filter_map(|line| parse_line(line)).collect()
+    }
+
+    fn ndt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn uptime_prefix() {
+        let uptime = uptime("kern.info: [   12.345678] usb 1-1: new device");
+        assert_eq!(uptime, Some(Duration::microseconds(12345678)));
+        assert_eq!(super::uptime("kern.info: usb 1-1: new device"), None);
+    }
+
+    #[test]
+    fn boots_from_banner() {
+        let boots = parse_kernel_boots(&lines(&[
+            "2022-09-01T13:30:02.00000 kern.notice: [    0.000000] Linux version 6.0.12_1",
+            "2022-09-01T13:30:02.00000 kern.info: [    1.500000] usb 1-1: new device",
+            "2022-09-01T13:35:00.00000 kern.warn: [  297.000000] wlan0: deauthenticating",
+            "2022-09-02T13:30:03.00000 kern.notice: [    0.000000] Linux version 6.0.12_1",
+            "2022-09-02T13:30:03.00000 kern.info: [    2.000000] EXT4-fs (sda1): mounted",
+        ]));
+        assert_eq!(
+            boots,
+            vec![
+                Boot {
+                    start: ndt("2022-09-02 13:30:01"),
+                    end: None,
+                },
+                Boot {
+                    start: ndt("2022-09-01 13:30:00.5"),
+                    end: Some(ndt("2022-09-02 13:30:00")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn boots_with_fractions() {
+        let boots = parse_kernel_boots(&lines(&[
+            "2022-09-01T13:30:02.68972 kern.notice: [    0.000000] Linux version 6.0.12_1",
+            "2022-09-01T13:30:02.68972 kern.info: [    1.500000] usb 1-1: new device",
+        ]));
+        assert_eq!(boots[0].start, ndt("2022-09-01 13:30:01.18972"));
+    }
+
+    #[test]
+    fn boots_from_uptime_reset() {
+        let boots = parse_kernel_boots(&lines(&[
+            "2022-09-01T13:35:00.00000 kern.warn: [  300.000000] wlan0: deauthenticating",
+            "2022-09-01T13:40:00.00000 kern.warn: [  600.000000] wlan0: authenticating",
+            "2022-09-02T13:30:05.00000 kern.info: [    5.000000] EXT4-fs (sda1): mounted",
+        ]));
+        assert_eq!(boots.len(), 2);
+        assert_eq!(boots[0].start, ndt("2022-09-02 13:30:00"));
+        assert_eq!(boots[1].start, ndt("2022-09-01 13:30:00"));
+    }
+
+    #[test]
+    fn boots_without_uptime() {
+        let boots = parse_kernel_boots(&lines(&[
+            "2022-09-02T13:32:53.00000 kern.debug: happy, happy, happy",
+            "2022-09-02T13:33:53.00000 kern.info: hello fren!",
+        ]));
+        assert_eq!(
+            boots,
+            vec![Boot {
+                start: ndt("2022-09-02 13:32:53"),
+                end: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn no_boots() {
+        assert!(parse_kernel_boots(&[]).is_empty());
+    }
+}
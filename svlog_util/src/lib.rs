@@ -2,14 +2,32 @@
 extern crate lazy_static;
 
 mod cache;
+mod kernel_boots;
+mod lines;
 mod log_priority;
 mod os_times;
+mod output_format;
+mod rate_rule;
 mod svlog_error;
+mod syslog_target;
+mod time_window;
+mod timezone;
+mod tzif;
+mod wtmp;
 
 pub mod regex;
 pub mod services;
 
 pub use cache::Cache;
+pub use kernel_boots::read_kernel_boots;
+pub use lines::lossy_lines;
 pub use log_priority::LogPriority;
 pub use os_times::*;
+pub use output_format::OutputFormat;
+pub use rate_rule::RateRule;
 pub use svlog_error::*;
+pub use syslog_target::{SyslogFormat, SyslogTarget, Transport};
+pub use time_window::TimeWindow;
+pub use timezone::*;
+pub use tzif::{LocalTimeType, Tzif};
+pub use wtmp::read_wtmp_boots;
@@ -0,0 +1,27 @@
+use std::io::BufRead;
+
+// Returns the lines of a reader like `BufRead::lines`, but decodes invalid
+// UTF-8 lossily instead of failing, so a single garbled line doesn't end the
+// reading. Reading stops at the first I/O error.
+pub fn lossy_lines<R: BufRead>(reader: R) -> impl Iterator<Item = String> {
+    reader.split(b'\n').map_while(Result::ok).map(|mut line| {
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        String::from_utf8_lossy(&line).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_utf8() {
+        let input: &[u8] = b"first\nbad \xff line\r\nlast";
+        assert_eq!(
+            lossy_lines(input).collect::<Vec<String>>(),
+            vec!["first", "bad \u{fffd} line", "last"]
+        );
+    }
+}
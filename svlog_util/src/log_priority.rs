@@ -15,6 +15,37 @@ impl LogPriority {
         }
     }
 
+    // Parses a priority (e.g. "warn") or a range of priorities (e.g. "warn..5",
+    // "..err" or "err.."). Returns the lowest and highest value.
+    pub fn parse_range(s: &str) -> Option<(Self, Self)> {
+        match s.split("..").collect::<Vec<&str>>()[..] {
+            [priority] => Self::parse(priority).map(|p| (p, p)),
+            [from, to] => {
+                let from = match from {
+                    "" => Some(Self::min()),
+                    _ => Self::parse(from),
+                };
+                let to = match to {
+                    "" => Some(Self::max()),
+                    _ => Self::parse(to),
+                };
+                from.zip(to)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    // Returns the name of the priority, e.g. "err".
+    pub fn name(&self) -> &'static str {
+        [
+            "emerg", "alert", "crit", "err", "warn", "notice", "info", "debug",
+        ][self.0 as usize]
+    }
+
     pub fn min() -> Self {
         LogPriority(0)
     }
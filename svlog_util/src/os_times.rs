@@ -1,45 +1,136 @@
-use std::{ops::Sub, process::Command};
+use std::{
+    cmp::Reverse,
+    ops::{RangeInclusive, Sub},
+    path::Path,
+};
 
 use chrono::{DateTime, Duration, NaiveDateTime};
-use chrono_tz::Tz;
-use snafu::{ensure, ResultExt};
+use serde::{Deserialize, Serialize};
+use snafu::ensure;
 use sysinfo::System;
 
-use crate::svlog_error::{
-    BootTimeNotFoundSnafu, CommandOutputSnafu, ParsingChronoSnafu, SvLogError, SvLogResult,
-    TimeZoneSnafu,
+use crate::{
+    kernel_boots::read_kernel_boots,
+    svlog_error::{BootTimeNotFoundSnafu, SvLogError, SvLogResult},
+    time_window::TimeWindow,
+    wtmp::read_wtmp_boots,
 };
 
-static DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
-
-pub fn local_tz() -> SvLogResult<Tz> {
-    let path = std::fs::read_link("/etc/localtime")
-        .context(CommandOutputSnafu {
-            message: "Could not read \"/etc/localtime\"".to_string(),
-        })?
-        .into_os_string()
-        .into_string()
-        .unwrap_or_else(|_| "".to_string());
-    let zone_str = path.split("zoneinfo/").last().unwrap_or("");
-    let zone = zone_str.parse::<Tz>();
-    ensure!(
-        zone.is_ok(),
-        TimeZoneSnafu {
-            message: format!("Could not find timezone \"{zone_str}\""),
+// Boots of the same machine found in wtmp and in the kernel logs are
+// considered to be the same if they started within this amount of seconds.
+static SAME_BOOT_SECONDS: i64 = 5 * 60;
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Boot {
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+}
+
+// Source of the boot table: the wtmp file, the kernel logs of socklog or both
+// of them combined.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BootSource {
+    Wtmp,
+    Logs,
+    Both,
+}
+
+impl BootSource {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "wtmp" => Some(BootSource::Wtmp),
+            "logs" => Some(BootSource::Logs),
+            "both" => Some(BootSource::Both),
+            _ => None,
         }
-    );
-    Ok(zone.unwrap())
+    }
 }
 
-pub fn boot_times(offset: usize) -> SvLogResult<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
-    if offset > 0 {
-        // FIXME: This only works for glibc
-        let boot_times = get_boot_time_with_offset(offset)?;
-        Ok(boot_times)
+// Returns the boots ordered from the current (i.e. last) boot to the first
+// one. With BootSource::Both, a missing wtmp file is not considered an error.
+pub fn boot_table(source: BootSource, wtmp_path: &Path, log_dir: &str) -> SvLogResult<Vec<Boot>> {
+    match source {
+        BootSource::Wtmp => read_wtmp_boots(wtmp_path),
+        BootSource::Logs => read_kernel_boots(log_dir),
+        BootSource::Both => {
+            let wtmp_boots = read_wtmp_boots(wtmp_path).unwrap_or_default();
+            let log_boots = read_kernel_boots(log_dir)?;
+            Ok(merge_boots(wtmp_boots, log_boots))
+        }
+    }
+}
+
+// Returns the time windows of the boots with the given offsets.
+pub fn boot_times(
+    offsets: &[RangeInclusive<usize>],
+    source: BootSource,
+    wtmp_path: &Path,
+    log_dir: &str,
+) -> SvLogResult<Vec<TimeWindow>> {
+    let use_system_boot_time = |offset: usize| offset == 0 && source == BootSource::Wtmp;
+    // NOTE: the current boot is known without reading the boot table
+    let (boots, known_boots) = if offsets
+        .iter()
+        .all(|range| use_system_boot_time(*range.end()))
+    {
+        (Vec::new(), 1)
     } else {
-        let since = get_last_boot_time()?;
-        Ok((Some(since), None))
+        let boots = boot_table(source, wtmp_path, log_dir)?;
+        let known_boots = boots.len();
+        (boots, known_boots)
+    };
+    expand_offsets(offsets, known_boots)?
+        .into_iter()
+        .map(|offset| {
+            if use_system_boot_time(offset) {
+                let since = get_last_boot_time()?;
+                Ok(TimeWindow::new(Some(since), None))
+            } else {
+                extract_boot_times(&boots, offset)
+            }
+        })
+        .collect()
+}
+
+// Returns the time windows of the boots with the given offsets in the boot
+// table.
+pub fn boot_table_times(
+    boots: &[Boot],
+    offsets: &[RangeInclusive<usize>],
+) -> SvLogResult<Vec<TimeWindow>> {
+    expand_offsets(offsets, boots.len())?
+        .into_iter()
+        .map(|offset| extract_boot_times(boots, offset))
+        .collect()
+}
+
+// Returns the sorted offsets in the ranges. Offsets beyond the known boots
+// fail before the ranges are expanded, so a range like "0..99999999999" never
+// gets larger than the boot table.
+fn expand_offsets(
+    offsets: &[RangeInclusive<usize>],
+    known_boots: usize,
+) -> SvLogResult<Vec<usize>> {
+    let mut expanded = Vec::new();
+    for range in offsets {
+        ensure!(
+            *range.end() < known_boots,
+            BootTimeNotFoundSnafu {
+                message: format!(
+                    "couldn't find boot with offset {}",
+                    (*range.start()).max(known_boots)
+                ),
+            }
+        );
+        expanded.extend(range.clone());
     }
+    expanded.sort_unstable();
+    expanded.dedup();
+    Ok(expanded)
+}
+
+pub fn host_name() -> Option<String> {
+    System::host_name()
 }
 
 fn get_last_boot_time() -> SvLogResult<NaiveDateTime> {
@@ -57,82 +148,105 @@ fn get_last_boot_time() -> SvLogResult<NaiveDateTime> {
     }
 }
 
-fn get_boot_time_with_offset(
-    offset: usize,
-) -> SvLogResult<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
-    // FIXME: on musl, "last" will throw an error as wtmp does not exist
-    let output_result = Command::new("last")
-        .arg("-a")
-        .arg("--time-format")
-        .arg("iso")
-        .env("TZ", "UTC")
-        .output();
-    match output_result {
-        Err(e) => Err(SvLogError::CommandOutputError {
-            message: "failed to retrieve output of `TZ=UTC last -a --time-format iso`".to_string(),
-            source: e,
-        }),
-        Ok(output) if !output.status.success() => Err(SvLogError::BootTimeNotFound {
-            message: format!(
-                "exit status {:?} for `TZ=UTC last -a --time-format iso`",
-                output.status.code().unwrap()
-            ),
-        }),
-        Ok(output) => {
-            let output_str = String::from_utf8(output.stdout).unwrap();
-            let boot_time_lines: Vec<String> = output_str
-                .split('\n')
-                .filter(|x| x.contains("system boot"))
-                .map(|x| x.to_string())
-                .collect();
-            extract_boot_times(boot_time_lines, offset)
-        }
-    }
-}
-
-fn extract_boot_times(
-    boot_time_lines: Vec<String>,
-    offset: usize,
-) -> SvLogResult<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
-    assert!(offset >= 1);
+pub fn extract_boot_times(boots: &[Boot], offset: usize) -> SvLogResult<TimeWindow> {
     ensure!(
-        boot_time_lines.len() > offset,
+        boots.len() > offset,
         BootTimeNotFoundSnafu {
             message: format!("couldn't find boot with offset {}", offset),
         }
     );
-    let offset_line = &boot_time_lines[offset];
-    ensure!(
-        offset_line.len() >= 41,
-        BootTimeNotFoundSnafu {
-            message: format!("can't find valid timestamp on line: {}", offset_line),
+    let boot = &boots[offset];
+    Ok(TimeWindow::new(Some(boot.start), boot.end))
+}
+
+// Merges two boot tables (ordered from the last to the first boot). Boots in
+// both tables are taken from the primary one, the others are inserted and the
+// end times are adjusted to not overlap with the following boot.
+fn merge_boots(primary: Vec<Boot>, secondary: Vec<Boot>) -> Vec<Boot> {
+    let mut boots = primary;
+    for boot in secondary {
+        let is_known = boots
+            .iter()
+            .any(|known| (known.start - boot.start).num_seconds().abs() <= SAME_BOOT_SECONDS);
+        if !is_known {
+            boots.push(boot);
         }
-    );
-    let from = Some(
-        NaiveDateTime::parse_from_str(&offset_line[22..41], DATE_FORMAT).context(
-            ParsingChronoSnafu {
-                line: &offset_line[22..41],
-            },
-        )?,
-    );
-    let until = if offset_line.len() >= 69 {
-        Some(
-            NaiveDateTime::parse_from_str(&offset_line[50..69], DATE_FORMAT).context(
-                ParsingChronoSnafu {
-                    line: &offset_line[50..69],
-                },
-            )?,
-        )
-    } else {
-        // NOTE: if no shutdown time is present, try to use the next boot time instead
-        let prev_line = &boot_time_lines[offset - 1];
-        Some(
-            NaiveDateTime::parse_from_str(&prev_line[22..41], DATE_FORMAT)
-                .context(ParsingChronoSnafu {
-                    line: &offset_line[22..41],
-                })?
-                .sub(Duration::seconds(1)),
-        )
-    };
-    Ok((from, until))
+    }
+    boots.sort_by_key(|boot| Reverse(boot.start));
+    for i in 1..boots.len() {
+        let latest_end = boots[i - 1].start.sub(Duration::seconds(1));
+        let end = boots[i].end.map_or(latest_end, |end| end.min(latest_end));
+        boots[i].end = Some(end);
+    }
+    if let Some(last) = boots.first_mut() {
+        last.end = None;
+    }
+    boots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ndt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn boot(start: &str, end: Option<&str>) -> Boot {
+        Boot {
+            start: ndt(start),
+            end: end.map(ndt),
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let wtmp = vec![
+            boot("2022-09-03 08:00:00", None),
+            boot("2022-09-01 13:30:00", Some("2022-09-01 13:40:00")),
+        ];
+        let logs = vec![
+            boot("2022-09-03 08:00:02", None),
+            boot("2022-09-02 13:30:01", Some("2022-09-03 08:00:01")),
+            boot("2022-09-01 13:30:01", Some("2022-09-02 13:30:00")),
+        ];
+        assert_eq!(
+            merge_boots(wtmp, logs),
+            vec![
+                boot("2022-09-03 08:00:00", None),
+                boot("2022-09-02 13:30:01", Some("2022-09-03 07:59:59")),
+                boot("2022-09-01 13:30:00", Some("2022-09-01 13:40:00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_empty() {
+        let logs = vec![boot("2022-09-03 08:00:02", None)];
+        assert_eq!(merge_boots(vec![], logs.clone()), logs);
+    }
+
+    #[test]
+    fn extract_not_found() {
+        let boots = vec![boot("2022-09-03 08:00:00", None)];
+        assert!(extract_boot_times(&boots, 1).is_err());
+        let times = extract_boot_times(&boots, 0).unwrap();
+        assert_eq!(
+            times,
+            TimeWindow::new(Some(ndt("2022-09-03 08:00:00")), None)
+        );
+    }
+
+    #[test]
+    fn expand_ranges() {
+        assert_eq!(
+            expand_offsets(&[2..=3, 0..=0, 1..=2], 4).unwrap(),
+            vec![0, 1, 2, 3]
+        );
+        // huge ranges fail without being expanded
+        let e = expand_offsets(&[1..=usize::MAX], 4).unwrap_err();
+        assert!(e.to_string().contains("offset 4"));
+        let e = expand_offsets(&[6..=8], 4).unwrap_err();
+        assert!(e.to_string().contains("offset 6"));
+    }
 }
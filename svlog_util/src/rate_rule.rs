@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use crate::{services::matches_pattern, LogPriority};
+
+// A limit for the number of log lines of a service within a sliding window,
+// e.g. 20 errors per minute from "kernel".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateRule {
+    // Shell-style pattern for the services the limit applies to, each
+    // service is counted on its own.
+    pub service: String,
+    pub min_priority: LogPriority,
+    pub max_priority: LogPriority,
+    // Number of lines within the window which are still fine.
+    pub limit: usize,
+    pub window: Duration,
+}
+
+impl RateRule {
+    // Parses rules like "kernel:..err:20/1m" (SERVICE:PRIORITIES:LIMIT/WINDOW).
+    // The service and the priorities are optional ("*" and all priorities),
+    // the window is given in seconds ("s"), minutes ("m"), hours ("h") or
+    // days ("d").
+    pub fn parse(s: &str) -> Option<Self> {
+        let (filters, rate) = match s.rsplit_once(':') {
+            Some((filters, rate)) => (Some(filters), rate),
+            None => (None, s),
+        };
+        let (service, priorities) = match filters.map(|filters| filters.split_once(':')) {
+            None => ("*", None),
+            Some(None) => (filters?, None),
+            Some(Some((service, priorities))) => (service, Some(priorities)),
+        };
+        let (min_priority, max_priority) = match priorities {
+            None | Some("") => (LogPriority::min(), LogPriority::max()),
+            Some(priorities) => LogPriority::parse_range(priorities)?,
+        };
+        let (limit, window) = rate.split_once('/')?;
+        Some(RateRule {
+            service: match service {
+                "" => "*".to_string(),
+                service => service.to_string(),
+            },
+            min_priority,
+            max_priority,
+            limit: limit.parse().ok()?,
+            window: parse_window(window)?,
+        })
+    }
+
+    // Returns whether lines of the service with the priority are counted.
+    pub fn applies_to(&self, service: &str, priority: &LogPriority) -> bool {
+        *priority >= self.min_priority
+            && *priority <= self.max_priority
+            && matches_pattern(&self.service, service)
+    }
+}
+
+// Parses durations like "30s", "5m", "1h" or "1d".
+fn parse_window(s: &str) -> Option<Duration> {
+    let unit = s.chars().last()?;
+    let amount: u64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        's' => amount,
+        'm' => amount.checked_mul(60)?,
+        'h' => amount.checked_mul(60 * 60)?,
+        'd' => amount.checked_mul(24 * 60 * 60)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds)).filter(|window| !window.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            RateRule::parse("kernel:..err:20/1m"),
+            Some(RateRule {
+                service: "kernel".to_string(),
+                min_priority: LogPriority::min(),
+                max_priority: LogPriority::parse("err").unwrap(),
+                limit: 20,
+                window: Duration::from_secs(60),
+            })
+        );
+        let rule = RateRule::parse("net*:5/30s").unwrap();
+        assert_eq!(rule.service, "net*");
+        assert_eq!(rule.max_priority, LogPriority::max());
+        assert_eq!(RateRule::parse("100/1h").unwrap().service, "*");
+        assert_eq!(
+            RateRule::parse("::3/2d").unwrap().window,
+            Duration::from_secs(2 * 24 * 60 * 60)
+        );
+        for invalid in [
+            "",
+            "20",
+            "20/",
+            "20/1",
+            "x/1m",
+            "20/0m",
+            "k:foo:20/1m",
+            "a:b:c:1/1m",
+        ] {
+            assert_eq!(RateRule::parse(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -1,4 +1,8 @@
 use regex::{Regex, RegexBuilder};
+use regex_syntax::ParserBuilder;
+use snafu::ResultExt;
+
+use crate::{InvalidRegexSnafu, SvLogResult};
 
 lazy_static! {
     pub static ref RE_DATE: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
@@ -7,13 +11,90 @@ lazy_static! {
     pub static ref RE_DATETIME_MIN: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}$").unwrap();
     pub static ref RE_DATETIME_SEC: Regex =
         Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}$").unwrap();
+    pub static ref RE_POSIX_TZ_FIXED: Regex =
+        Regex::new(r"^(?:[A-Za-z]{3,}|<[^>]+>)([+-]?)(\d{1,2})$").unwrap();
+    pub static ref RE_RELATIVE_TIME: Regex = Regex::new(r"^-(\d{1,9})([smhd])$").unwrap();
+    pub static ref RE_KERNEL_UPTIME: Regex = Regex::new(r"\[\s*(\d+)\.(\d{1,9})\]").unwrap();
+}
+
+// Limits for the regexes given by users so that pathological patterns like
+// "(a{1000}){1000}" fail instead of using lots of memory and time.
+static SIZE_LIMIT: usize = 1 << 20;
+static NEST_LIMIT: u32 = 64;
+
+pub fn build_regex(pattern: &str, case_insensitive: bool) -> SvLogResult<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .size_limit(SIZE_LIMIT)
+        .dfa_size_limit(SIZE_LIMIT)
+        .nest_limit(NEST_LIMIT)
+        .build()
+        .context(InvalidRegexSnafu { pattern })
+}
+
+// Describes why pattern is invalid. Syntax errors contain the position (in
+// characters, starting at 1) of the error in the pattern.
+pub fn describe_regex_error(pattern: &str, error: &regex::Error) -> String {
+    match error {
+        regex::Error::Syntax(_) => {
+            let parsed = ParserBuilder::new()
+                .nest_limit(NEST_LIMIT)
+                .build()
+                .parse(pattern);
+            let (kind, span) = match &parsed {
+                Err(regex_syntax::Error::Parse(e)) => (e.kind().to_string(), e.span()),
+                Err(regex_syntax::Error::Translate(e)) => (e.kind().to_string(), e.span()),
+                _ => return error.to_string(),
+            };
+            let position = pattern[..span.start.offset].chars().count() + 1;
+            format!("{kind} at position {position}")
+        }
+        regex::Error::CompiledTooBig(limit) => {
+            format!("the compiled pattern exceeds the size limit of {limit} bytes")
+        }
+        _ => error.to_string(),
+    }
 }
 
-pub fn build_regex(pattern: &Option<String>, case_insensitive: bool) -> Option<Regex> {
-    pattern.as_ref().map(|pattern| {
-        RegexBuilder::new(&pattern[..])
-            .case_insensitive(case_insensitive)
-            .build()
-            .unwrap()
-    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(pattern: &str) -> String {
+        match build_regex(pattern, false) {
+            Err(crate::SvLogError::InvalidRegexError { source, .. }) => {
+                describe_regex_error(pattern, &source)
+            }
+            _ => panic!("expected InvalidRegexError"),
+        }
+    }
+
+    #[test]
+    fn valid() {
+        assert!(build_regex("kern\\.(err|crit)", true).is_ok());
+    }
+
+    #[test]
+    fn unclosed_group() {
+        assert_eq!(error("ab(c"), "unclosed group at position 3");
+    }
+
+    #[test]
+    fn position_in_characters() {
+        assert_eq!(
+            error("äö[z-a]"),
+            "invalid character class range, the start must be <= the end at position 4"
+        );
+    }
+
+    #[test]
+    fn too_big() {
+        assert!(error("(a{1000}){1000}").contains("size limit"));
+    }
+
+    #[test]
+    fn too_deep() {
+        let pattern = format!("{}a{}", "(".repeat(100), ")".repeat(100));
+        assert!(error(&pattern).ends_with("nested parentheses/brackets (64) at position 65"));
+    }
 }
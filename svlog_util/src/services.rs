@@ -1,63 +1,179 @@
-use std::path::{Path, PathBuf};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
-use glob::glob;
+use glob::{glob, Pattern};
+use snafu::{ensure, ResultExt};
 
-use crate::{SvLogError, SvLogResult};
+use crate::{ReadLogDirSnafu, ServiceNotFoundSnafu, SvLogResult};
 
 static GLOB_ALL_FILES: &[&str] = &["/current", "/*.[su]"];
 
-pub fn check_services(log_dir: &str, services: &Vec<String>) -> SvLogResult<()> {
-    let all_services = all_services(log_dir);
-    for service in services {
-        if !all_services.contains(service) {
-            return Err(SvLogError::ServiceNotFoundError {
-                service: service.clone(),
-            });
+// The log files found by file_paths and the directories which couldn't be
+// read, e.g. because of missing permissions.
+#[derive(Default, Debug)]
+pub struct LogPaths {
+    pub files: Vec<PathBuf>,
+    pub unreadable: Vec<(PathBuf, io::ErrorKind)>,
+}
+
+// Returns the names of the services in log_dir selected by the patterns, see
+// filter_services.
+pub fn select_services(
+    log_dir: &str,
+    patterns: &[String],
+    excludes: &[String],
+) -> SvLogResult<Vec<String>> {
+    filter_services(all_services(log_dir)?, patterns, excludes)
+}
+
+// Returns the services selected by the patterns ordered by name. Patterns are
+// shell-style globs (e.g. "net*"), patterns starting with "^" and the excludes
+// remove the matching services. Without including pattern, all services are
+// selected. Fails if any pattern matches no service.
+pub fn filter_services(
+    mut all_services: Vec<String>,
+    patterns: &[String],
+    excludes: &[String],
+) -> SvLogResult<Vec<String>> {
+    let (excluded, included): (Vec<&str>, Vec<&str>) = patterns
+        .iter()
+        .map(|pattern| pattern.as_str())
+        .partition(|pattern| pattern.starts_with('^'));
+    let excluded: Vec<&str> = excluded
+        .iter()
+        .map(|pattern| &pattern[1..])
+        .chain(excludes.iter().map(|pattern| pattern.as_str()))
+        .collect();
+    all_services.sort();
+    check_services(&all_services, included.iter().chain(excluded.iter()))?;
+    Ok(all_services
+        .into_iter()
+        .filter(|service| {
+            included.is_empty() || included.iter().any(|p| matches_pattern(p, service))
+        })
+        .filter(|service| !excluded.iter().any(|p| matches_pattern(p, service)))
+        .collect())
+}
+
+// Reports all patterns which don't match any of the services.
+fn check_services<'a>(
+    services: &[String],
+    patterns: impl Iterator<Item = &'a &'a str>,
+) -> SvLogResult<()> {
+    let not_found: Vec<String> = patterns
+        .filter(|pattern| !services.iter().any(|s| matches_pattern(pattern, s)))
+        .map(|pattern| pattern.to_string())
+        .collect();
+    ensure!(
+        not_found.is_empty(),
+        ServiceNotFoundSnafu {
+            services: not_found
         }
-    }
+    );
     Ok(())
 }
 
-pub fn file_paths(log_dir: &str, services: &[String]) -> Vec<PathBuf> {
-    let mut service_globs = services.to_owned();
+// Matches a service name against a shell-style pattern. Invalid patterns are
+// compared literally.
+pub fn matches_pattern(pattern: &str, service: &str) -> bool {
+    match Pattern::new(pattern) {
+        Ok(pattern) => pattern.matches(service),
+        Err(_) => pattern == service,
+    }
+}
+
+// Returns the log files of the services (of all if empty). Service names are
+// matched literally.
+pub fn file_paths(log_dir: &str, services: &[String]) -> LogPaths {
+    let mut service_globs: Vec<String> = services
+        .iter()
+        .map(|service| Pattern::escape(service))
+        .collect();
     if service_globs.is_empty() {
         service_globs.push(String::from("**"));
     }
-    service_globs
-        .iter()
-        .flat_map(|g| service_file_paths(log_dir, g, GLOB_ALL_FILES).into_iter())
-        .collect()
+    let mut paths = LogPaths::default();
+    for service_glob in &service_globs {
+        service_file_paths(log_dir, service_glob, GLOB_ALL_FILES, &mut paths);
+    }
+    paths
 }
 
-pub fn list_services(log_dir: &str) {
-    for service in all_services(log_dir).iter() {
-        println!(" - {}", service);
-    }
+// Returns the names of the directories in log_dir, names which aren't valid
+// UTF-8 are skipped.
+pub fn all_services(log_dir: &str) -> SvLogResult<Vec<String>> {
+    let entries = Path::new(log_dir)
+        .read_dir()
+        .context(ReadLogDirSnafu { path: log_dir })?;
+    Ok(entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(String::from))
+        .collect())
 }
 
-pub fn all_services(log_dir: &str) -> Vec<String> {
-    let mut services = Vec::new();
-    let log_dir = Path::new(&log_dir);
-    for entry in log_dir.read_dir().expect("read_dir call failed").flatten() {
-        let service_dir = entry.path();
-        if service_dir.is_dir() {
-            let filename = service_dir.file_name().unwrap().to_str().unwrap();
-            services.push(filename.to_string());
+fn service_file_paths(
+    log_dir: &str,
+    service_glob: &str,
+    file_globs: &[&str],
+    paths: &mut LogPaths,
+) {
+    for glob_str_ext in file_globs {
+        // NOTE: the log directory may be given with or without trailing slash
+        let glob_str = format!(
+            "{}/{}{}",
+            Pattern::escape(log_dir.trim_end_matches('/')),
+            service_glob,
+            glob_str_ext
+        );
+        for entry in glob(&glob_str[..]).expect("Failed to read glob pattern") {
+            match entry {
+                Ok(path) => paths.files.push(path),
+                Err(e) => {
+                    // NOTE: each glob reports the directories it can't read
+                    if !paths.unreadable.iter().any(|(dir, _)| dir == e.path()) {
+                        paths
+                            .unreadable
+                            .push((e.path().to_path_buf(), e.error().kind()));
+                    }
+                }
+            }
         }
     }
-    services
 }
 
-fn service_file_paths(log_dir: &str, service_glob: &str, file_globs: &[&str]) -> Vec<PathBuf> {
-    let mut files = Vec::new();
-    for glob_str_ext in file_globs {
-        let glob_str = String::from(log_dir) + service_glob + glob_str_ext;
-        for entry in glob(&glob_str[..])
-            .expect("Failed to read glob pattern")
-            .flatten()
-        {
-            files.push(entry);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        assert!(matches_pattern("net*", "network"));
+        assert!(matches_pattern("*ssh*", "openssh"));
+        assert!(matches_pattern("cron", "cron"));
+        assert!(!matches_pattern("cron", "crond"));
+        assert!(matches_pattern("[x", "[x"));
+    }
+
+    #[test]
+    fn not_found() {
+        let services = vec!["cron".to_string(), "sshd".to_string()];
+        assert!(check_services(&services, ["cr*", "sshd"].iter()).is_ok());
+        match check_services(&services, ["cr*", "x*", "y"].iter()) {
+            Err(crate::SvLogError::ServiceNotFoundError { services }) => {
+                assert_eq!(services, vec!["x*", "y"])
+            }
+            _ => panic!("expected ServiceNotFoundError"),
         }
     }
-    files
+
+    #[test]
+    fn missing_log_dir() {
+        assert!(matches!(
+            all_services("/nonexistent/socklog/"),
+            Err(crate::SvLogError::ReadLogDirError { .. })
+        ));
+    }
 }
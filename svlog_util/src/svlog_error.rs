@@ -43,6 +43,55 @@ pub enum SvLogError {
         source: std::io::Error,
     },
 
+    #[snafu(display("ReadLogDirError: {path}: {source}"))]
+    ReadLogDirError {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("ReadLogFileError: {path}: {source}"))]
+    ReadLogFileError {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("ReadArchiveError: {path}: {source}"))]
+    ReadArchiveError {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("WriteBundleError: {path}: {source}"))]
+    WriteBundleError {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("CursorError: {path}: {message}"))]
+    CursorError { path: String, message: String },
+
+    #[snafu(display("ManifestError: {message}"))]
+    ManifestError { message: String },
+
+    #[snafu(display("RulesError: {path}:{line}: {message}"))]
+    RulesError {
+        path: String,
+        line: usize,
+        message: String,
+    },
+
+    #[snafu(display("ForwardError: {target}: {source}"))]
+    ForwardError {
+        target: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("LokiError: {url}: {message}"))]
+    LokiError { url: String, message: String },
+
+    #[snafu(display("ServeError: {address}: {message}"))]
+    ServeError { address: String, message: String },
+
     #[snafu(display("WatchFilesError: {message}"))]
     WatchFilesError {
         message: String,
@@ -58,6 +107,38 @@ pub enum SvLogError {
         source: notify::Error,
     },
 
-    #[snafu(display("Service \"{service}\" not found"))]
-    ServiceNotFoundError { service: String },
+    #[snafu(display(
+        "Invalid regular expression \"{pattern}\": {}",
+        crate::regex::describe_regex_error(pattern, source)
+    ))]
+    InvalidRegexError {
+        pattern: String,
+        source: regex::Error,
+    },
+
+    #[snafu(display("{}", services_not_found(services)))]
+    ServiceNotFoundError { services: Vec<String> },
+}
+
+impl SvLogError {
+    // Returns whether the error was caused by missing permissions, e.g. for a
+    // log directory which is only readable by the "socklog" group.
+    pub fn is_permission_denied(&self) -> bool {
+        match self {
+            SvLogError::OpenFileError { source, .. }
+            | SvLogError::ReadLogDirError { source, .. }
+            | SvLogError::ReadLogFileError { source, .. } => {
+                source.kind() == std::io::ErrorKind::PermissionDenied
+            }
+            _ => false,
+        }
+    }
+}
+
+fn services_not_found(services: &[String]) -> String {
+    let quoted: Vec<String> = services.iter().map(|s| format!("\"{s}\"")).collect();
+    match quoted.len() {
+        1 => format!("Service {} not found", quoted[0]),
+        _ => format!("Services {} not found", quoted.join(", ")),
+    }
 }
@@ -0,0 +1,160 @@
+use std::fmt::{self, Display, Formatter};
+
+// Framing of the messages sent to a syslog server.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SyslogFormat {
+    Rfc5424,
+    Rfc3164,
+}
+
+impl SyslogFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "rfc5424" => Some(SyslogFormat::Rfc5424),
+            "rfc3164" => Some(SyslogFormat::Rfc3164),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Transport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+impl Transport {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "udp" => Some(Transport::Udp),
+            "tcp" => Some(Transport::Tcp),
+            "tls" => Some(Transport::Tls),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Transport::Udp => "udp",
+            Transport::Tcp => "tcp",
+            Transport::Tls => "tls",
+        }
+    }
+
+    // Ports assigned by IANA for syslog (RFC 5426, RFC 6587 and RFC 5425)
+    fn default_port(&self) -> u16 {
+        match self {
+            Transport::Udp => 514,
+            Transport::Tcp => 601,
+            Transport::Tls => 6514,
+        }
+    }
+}
+
+// A syslog server given like "tcp://logs.example.com:601". The port defaults
+// to the one of the transport.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SyslogTarget {
+    pub transport: Transport,
+    pub host: String,
+    pub port: u16,
+}
+
+impl SyslogTarget {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (transport, address) = s.split_once("://")?;
+        let transport = Transport::parse(transport)?;
+        // NOTE: IPv6 addresses with port are given in brackets, e.g. "[::1]:514"
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port))
+                if !host.is_empty() && (!host.contains(':') || host.ends_with(']')) =>
+            {
+                (host, port.parse().ok()?)
+            }
+            _ => (address, transport.default_port()),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() || host.contains('/') {
+            return None;
+        }
+        Some(SyslogTarget {
+            transport,
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    // Returns the address to connect to, e.g. "localhost:514" or "[::1]:514".
+    pub fn address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl Display for SyslogTarget {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}://{}", self.transport.name(), self.address())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(transport: Transport, host: &str, port: u16) -> Option<SyslogTarget> {
+        Some(SyslogTarget {
+            transport,
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    #[test]
+    fn parse_target() {
+        assert_eq!(
+            SyslogTarget::parse("udp://localhost"),
+            target(Transport::Udp, "localhost", 514)
+        );
+        assert_eq!(
+            SyslogTarget::parse("tcp://10.0.0.1:1514"),
+            target(Transport::Tcp, "10.0.0.1", 1514)
+        );
+        assert_eq!(
+            SyslogTarget::parse("tls://logs.example.com"),
+            target(Transport::Tls, "logs.example.com", 6514)
+        );
+        assert_eq!(
+            SyslogTarget::parse("udp://[::1]:5514"),
+            target(Transport::Udp, "::1", 5514)
+        );
+        assert_eq!(
+            SyslogTarget::parse("udp://::1"),
+            target(Transport::Udp, "::1", 514)
+        );
+        for invalid in [
+            "localhost",
+            "http://localhost",
+            "tcp://",
+            "tcp://host:x",
+            "tcp://a/b",
+        ] {
+            assert_eq!(SyslogTarget::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn display_target() {
+        let target = SyslogTarget::parse("udp://[::1]").unwrap();
+        assert_eq!(target.to_string(), "udp://[::1]:514");
+        assert_eq!(
+            SyslogTarget::parse("tcp://localhost:601")
+                .unwrap()
+                .address(),
+            "localhost:601"
+        );
+    }
+}
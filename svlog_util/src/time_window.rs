@@ -0,0 +1,86 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+// Period of time between since and until (both included). A missing bound
+// means that the window is open on that side.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+impl TimeWindow {
+    pub fn new(since: Option<NaiveDateTime>, until: Option<NaiveDateTime>) -> Self {
+        TimeWindow { since, until }
+    }
+
+    pub fn contains(&self, time: &NaiveDateTime) -> bool {
+        match (self.since, self.until) {
+            (Some(since), _) if *time < since => false,
+            (_, Some(until)) if *time > until => false,
+            _ => true,
+        }
+    }
+
+    // Returns the period contained in both windows or None if they don't
+    // overlap.
+    pub fn intersect(&self, other: &TimeWindow) -> Option<TimeWindow> {
+        let since = self.since.max(other.since);
+        let until = match (self.until, other.until) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        match (since, until) {
+            (Some(since), Some(until)) if since > until => None,
+            _ => Some(TimeWindow { since, until }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ndt(s: &str) -> Option<NaiveDateTime> {
+        Some(NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap())
+    }
+
+    #[test]
+    fn contains() {
+        let window = TimeWindow::new(ndt("2022-09-01 10:00:00"), ndt("2022-09-01 12:00:00"));
+        assert!(window.contains(&ndt("2022-09-01 10:00:00").unwrap()));
+        assert!(window.contains(&ndt("2022-09-01 12:00:00").unwrap()));
+        assert!(!window.contains(&ndt("2022-09-01 09:59:59").unwrap()));
+        assert!(!window.contains(&ndt("2022-09-01 12:00:01").unwrap()));
+        assert!(TimeWindow::default().contains(&ndt("2022-09-01 12:00:01").unwrap()));
+    }
+
+    #[test]
+    fn intersect() {
+        let boot = TimeWindow::new(ndt("2022-09-01 10:00:00"), ndt("2022-09-01 12:00:00"));
+        let since = TimeWindow::new(ndt("2022-09-01 11:00:00"), None);
+        assert_eq!(
+            boot.intersect(&since),
+            Some(TimeWindow::new(
+                ndt("2022-09-01 11:00:00"),
+                ndt("2022-09-01 12:00:00")
+            ))
+        );
+        let until = TimeWindow::new(None, ndt("2022-09-01 11:00:00"));
+        assert_eq!(
+            until.intersect(&boot),
+            Some(TimeWindow::new(
+                ndt("2022-09-01 10:00:00"),
+                ndt("2022-09-01 11:00:00")
+            ))
+        );
+        assert_eq!(boot.intersect(&TimeWindow::default()), Some(boot));
+    }
+
+    #[test]
+    fn intersect_disjoint() {
+        let boot = TimeWindow::new(ndt("2022-09-01 10:00:00"), ndt("2022-09-01 12:00:00"));
+        let since = TimeWindow::new(ndt("2022-09-02 00:00:00"), None);
+        assert_eq!(boot.intersect(&since), None);
+    }
+}
@@ -0,0 +1,280 @@
+use std::path::Path;
+
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+use crate::{
+    regex::RE_POSIX_TZ_FIXED,
+    svlog_error::{SvLogError, SvLogResult},
+    tzif::Tzif,
+};
+
+static LOCALTIME_PATH: &str = "/etc/localtime";
+static TIMEZONE_PATH: &str = "/etc/timezone";
+static SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// Determines the local timezone. The sources are tried in the following order:
+//
+//  1. the TZ environment variable
+//  2. the target of the "/etc/localtime" symlink
+//  3. the name in "/etc/timezone"
+//  4. the TZif data of "/etc/localtime" matched against the timezone database
+//
+// If none of them yields a timezone, a warning is printed and UTC is used.
+pub fn local_tz() -> Tz {
+    let sources: [fn() -> Option<Tz>; 4] = [
+        env_tz,
+        || localtime_link_tz(Path::new(LOCALTIME_PATH)),
+        || timezone_file_tz(Path::new(TIMEZONE_PATH)),
+        || tzif_file_tz(Path::new(LOCALTIME_PATH)),
+    ];
+    sources
+        .iter()
+        .find_map(|source| source())
+        .unwrap_or_else(|| {
+            eprintln!("Warning: could not determine the local timezone, using UTC");
+            Tz::UTC
+        })
+}
+
+// Policy to convert local times which are ambiguous (e.g. 02:30 when the
+// clocks are turned back from 03:00 to 02:00) or which don't exist (e.g. 02:30
+// when the clocks are turned forward from 02:00 to 03:00).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DstPolicy {
+    Earliest,
+    Latest,
+    Error,
+}
+
+impl DstPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "earliest" => Some(DstPolicy::Earliest),
+            "latest" => Some(DstPolicy::Latest),
+            "error" => Some(DstPolicy::Error),
+            _ => None,
+        }
+    }
+
+    // Converts the local time in the timezone tz to UTC. Ambiguous times are
+    // resolved to the earlier or later point in time. Nonexistent times are
+    // resolved to the moment of the transition with both policies.
+    pub fn to_utc(&self, tz: &Tz, local: &NaiveDateTime) -> SvLogResult<NaiveDateTime> {
+        match (tz.from_local_datetime(local), self) {
+            (LocalResult::Single(time), _) => Ok(time.naive_utc()),
+            (LocalResult::Ambiguous(a, b), DstPolicy::Earliest) => {
+                Ok(a.naive_utc().min(b.naive_utc()))
+            }
+            (LocalResult::Ambiguous(a, b), DstPolicy::Latest) => {
+                Ok(a.naive_utc().max(b.naive_utc()))
+            }
+            (LocalResult::Ambiguous(a, b), DstPolicy::Error) => Err(SvLogError::TimeZoneError {
+                message: format!(
+                    "Local time \"{}\" is ambiguous in timezone \"{}\" ({} or {})",
+                    local,
+                    tz,
+                    a.fixed_offset(),
+                    b.fixed_offset()
+                ),
+            }),
+            (LocalResult::None, DstPolicy::Error) => Err(SvLogError::TimeZoneError {
+                message: format!(
+                    "Local time \"{}\" does not exist in timezone \"{}\"",
+                    local, tz
+                ),
+            }),
+            (LocalResult::None, _) => Ok(gap_transition(tz, local)),
+        }
+    }
+}
+
+// Finds the moment of the transition which causes the gap around the
+// nonexistent local time.
+fn gap_transition(tz: &Tz, local: &NaiveDateTime) -> NaiveDateTime {
+    let offset = |timestamp: i64| {
+        let utc = DateTime::from_timestamp(timestamp, 0).unwrap().naive_utc();
+        tz.offset_from_utc_datetime(&utc).fix().local_minus_utc()
+    };
+    let local_timestamp = local.and_utc().timestamp();
+    let offset_before = offset(local_timestamp - SECONDS_PER_DAY);
+    let offset_after = offset(local_timestamp + SECONDS_PER_DAY);
+    let mut low = local_timestamp - i64::from(offset_after);
+    let mut high = local_timestamp - i64::from(offset_before) + 1;
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if offset(middle) == offset_before {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    DateTime::from_timestamp(high, 0).unwrap().naive_utc()
+}
+
+fn env_tz() -> Option<Tz> {
+    let tz_var = std::env::var("TZ")
+        .ok()
+        .filter(|tz_var| !tz_var.is_empty())?;
+    let zone = tz_from_value(&tz_var);
+    if zone.is_none() {
+        eprintln!("Warning: ignoring unsupported timezone \"{tz_var}\" in TZ");
+    }
+    zone
+}
+
+// Interprets the value of the TZ environment variable. Supported are zone
+// names ("Europe/Zurich"), paths to TZif files ("/usr/share/zoneinfo/UTC")
+// optionally prefixed with a colon, and POSIX strings with a fixed offset
+// ("EST5").
+fn tz_from_value(value: &str) -> Option<Tz> {
+    let value = value.trim_start_matches(':');
+    if let Ok(zone) = zone_name(value).parse::<Tz>() {
+        Some(zone)
+    } else if value.starts_with('/') {
+        tzif_file_tz(Path::new(value))
+    } else {
+        posix_fixed_tz(value)
+    }
+}
+
+fn posix_fixed_tz(value: &str) -> Option<Tz> {
+    let captures = RE_POSIX_TZ_FIXED.captures(value)?;
+    let hours: u8 = captures[2].parse().ok()?;
+    if hours == 0 {
+        return Some(Tz::UTC);
+    }
+    // NOTE: POSIX and "Etc/GMT" offsets are both positive west of Greenwich
+    let sign = if &captures[1] == "-" { "-" } else { "+" };
+    format!("Etc/GMT{sign}{hours}").parse::<Tz>().ok()
+}
+
+fn localtime_link_tz(path: &Path) -> Option<Tz> {
+    let target = std::fs::read_link(path).ok()?;
+    zone_name(target.to_str()?).parse::<Tz>().ok()
+}
+
+fn timezone_file_tz(path: &Path) -> Option<Tz> {
+    let content = std::fs::read_to_string(path).ok()?;
+    content.trim().parse::<Tz>().ok()
+}
+
+fn tzif_file_tz(path: &Path) -> Option<Tz> {
+    let data = std::fs::read(path).ok()?;
+    Tzif::parse(&data).ok()?.find_tz()
+}
+
+fn zone_name(path: &str) -> &str {
+    path.rsplit("zoneinfo/").next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn value_zone_name() {
+        assert_eq!(tz_from_value("Europe/Zurich"), Some(Tz::Europe__Zurich));
+        assert_eq!(tz_from_value(":Asia/Tokyo"), Some(Tz::Asia__Tokyo));
+    }
+
+    #[test]
+    fn value_zoneinfo_path() {
+        let tz = tz_from_value("/usr/share/zoneinfo/America/New_York");
+        assert_eq!(tz, Some(Tz::America__New_York));
+    }
+
+    #[test]
+    fn value_tzif_path() {
+        let tz = tz_from_value(&fixture("America_New_York"));
+        assert_eq!(tz, Some(Tz::America__New_York));
+    }
+
+    #[test]
+    fn value_posix_fixed() {
+        assert_eq!(tz_from_value("UTC0"), Some(Tz::UTC));
+        assert_eq!(tz_from_value("EST5"), Some(Tz::Etc__GMTPlus5));
+        assert_eq!(tz_from_value("<+03>-3"), Some(Tz::Etc__GMTMinus3));
+    }
+
+    #[test]
+    fn value_unsupported() {
+        assert_eq!(tz_from_value("CET-1CEST,M3.5.0,M10.5.0/3"), None);
+        assert_eq!(tz_from_value("Foo/Bar"), None);
+    }
+
+    #[test]
+    fn timezone_file() {
+        let dir = std::env::temp_dir().join("svlog_timezone_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timezone");
+        std::fs::write(&path, "Europe/Zurich\n").unwrap();
+        assert_eq!(timezone_file_tz(&path), Some(Tz::Europe__Zurich));
+    }
+
+    fn ndt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn dst_single() {
+        let tz = Tz::Europe__Zurich;
+        let utc = DstPolicy::Error.to_utc(&tz, &ndt("2022-09-02 15:32:53"));
+        assert_eq!(utc.unwrap(), ndt("2022-09-02 13:32:53"));
+    }
+
+    #[test]
+    fn dst_europe_fold() {
+        let tz = Tz::Europe__Zurich;
+        let local = ndt("2022-10-30 02:30:00");
+        let earliest = DstPolicy::Earliest.to_utc(&tz, &local);
+        assert_eq!(earliest.unwrap(), ndt("2022-10-30 00:30:00"));
+        let latest = DstPolicy::Latest.to_utc(&tz, &local);
+        assert_eq!(latest.unwrap(), ndt("2022-10-30 01:30:00"));
+        assert!(DstPolicy::Error.to_utc(&tz, &local).is_err());
+    }
+
+    #[test]
+    fn dst_europe_gap() {
+        let tz = Tz::Europe__Zurich;
+        let local = ndt("2022-03-27 02:30:00");
+        let earliest = DstPolicy::Earliest.to_utc(&tz, &local);
+        assert_eq!(earliest.unwrap(), ndt("2022-03-27 01:00:00"));
+        let latest = DstPolicy::Latest.to_utc(&tz, &local);
+        assert_eq!(latest.unwrap(), ndt("2022-03-27 01:00:00"));
+        assert!(DstPolicy::Error.to_utc(&tz, &local).is_err());
+    }
+
+    #[test]
+    fn dst_us_fold() {
+        let tz = Tz::America__New_York;
+        let local = ndt("2022-11-06 01:30:00");
+        let earliest = DstPolicy::Earliest.to_utc(&tz, &local);
+        assert_eq!(earliest.unwrap(), ndt("2022-11-06 05:30:00"));
+        let latest = DstPolicy::Latest.to_utc(&tz, &local);
+        assert_eq!(latest.unwrap(), ndt("2022-11-06 06:30:00"));
+    }
+
+    #[test]
+    fn dst_us_gap() {
+        let tz = Tz::America__New_York;
+        for local in ["2022-03-13 02:00:00", "2022-03-13 02:59:59"] {
+            let utc = DstPolicy::Earliest.to_utc(&tz, &ndt(local));
+            assert_eq!(utc.unwrap(), ndt("2022-03-13 07:00:00"));
+        }
+        let error = DstPolicy::Error.to_utc(&tz, &ndt("2022-03-13 02:30:00"));
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn missing_files() {
+        let path = Path::new("/nonexistent/localtime");
+        assert_eq!(localtime_link_tz(path), None);
+        assert_eq!(timezone_file_tz(path), None);
+        assert_eq!(tzif_file_tz(path), None);
+    }
+}
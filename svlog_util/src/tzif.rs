@@ -0,0 +1,252 @@
+use chrono::{DateTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetName, Tz, TZ_VARIANTS};
+use snafu::ensure;
+
+use crate::svlog_error::{SvLogResult, TimeZoneSnafu};
+
+// Minimal reader for TZif files (RFC 8536) like "/etc/localtime". Only the
+// transition table is read, the POSIX TZ string in the footer is ignored.
+
+static MAGIC: &[u8] = b"TZif";
+static HEADER_LEN: usize = 44;
+static SAMPLE_INTERVAL: usize = 30 * 24 * 60 * 60;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LocalTimeType {
+    pub utc_offset: i32,
+    pub abbreviation: String,
+}
+
+#[derive(Debug)]
+pub struct Tzif {
+    transitions: Vec<(i64, usize)>,
+    local_time_types: Vec<LocalTimeType>,
+}
+
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> SvLogResult<Self> {
+        ensure!(
+            data.len() >= HEADER_LEN && &data[..4] == MAGIC,
+            TimeZoneSnafu {
+                message: "Invalid TZif header".to_string(),
+            }
+        );
+        // NOTE: the counts are unsigned
+        let count = |i: usize| {
+            let pos = 20 + 4 * i;
+            u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize
+        };
+        Ok(Header {
+            version: data[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        })
+    }
+
+    // Returns the length of the data after the header, None if the counts are
+    // too large to be valid.
+    fn data_len(&self, time_size: usize) -> Option<usize> {
+        [
+            self.timecnt.checked_mul(time_size + 1)?,
+            self.typecnt.checked_mul(6)?,
+            self.charcnt,
+            self.leapcnt.checked_mul(time_size + 4)?,
+            self.isstdcnt,
+            self.isutcnt,
+        ]
+        .into_iter()
+        .try_fold(0usize, usize::checked_add)
+    }
+}
+
+impl Tzif {
+    pub fn parse(data: &[u8]) -> SvLogResult<Self> {
+        let header = Header::parse(data)?;
+        if header.version >= b'2' {
+            // NOTE: version 2+ files repeat the data with 64-bit transition times
+            let v2_start = header
+                .data_len(4)
+                .and_then(|len| len.checked_add(HEADER_LEN));
+            let v2_data = v2_start.and_then(|start| data.get(start..)).unwrap_or(&[]);
+            let v2_header = Header::parse(v2_data)?;
+            Self::parse_data(&v2_data[HEADER_LEN..], &v2_header, 8)
+        } else {
+            Self::parse_data(&data[HEADER_LEN..], &header, 4)
+        }
+    }
+
+    // Returns the local time type in effect at the given unix timestamp.
+    pub fn local_time_type(&self, timestamp: i64) -> &LocalTimeType {
+        let count = self
+            .transitions
+            .partition_point(|(transition, _)| *transition <= timestamp);
+        let index = match count {
+            0 => 0,
+            _ => self.transitions[count - 1].1,
+        };
+        &self.local_time_types[index]
+    }
+
+    // Finds a timezone of the chrono-tz database which has the same offsets
+    // and abbreviations between 1970 and the last transition. The offsets are
+    // compared at all transitions and every 30 days in between. Timezones
+    // which are aliases of each other can't be distinguished and the database
+    // doesn't tell which name is canonical. As it is sorted by name, the first
+    // alias in alphabetical order is used, e.g. "Europe/Busingen" for
+    // "Europe/Zurich". Their times are the same, only the name differs.
+    pub fn find_tz(&self) -> Option<Tz> {
+        let last = self
+            .transitions
+            .last()
+            .map(|(transition, _)| *transition)
+            .unwrap_or_else(|| Utc::now().timestamp());
+        let mut timestamps: Vec<i64> = (0..=last).step_by(SAMPLE_INTERVAL).collect();
+        timestamps.extend(
+            self.transitions
+                .iter()
+                .map(|(transition, _)| *transition)
+                .filter(|transition| *transition >= 0),
+        );
+        let samples: Vec<(DateTime<Utc>, &LocalTimeType)> = timestamps
+            .into_iter()
+            .filter_map(|ts| Some((DateTime::from_timestamp(ts, 0)?, self.local_time_type(ts))))
+            .collect();
+        TZ_VARIANTS.iter().copied().find(|tz| {
+            samples.iter().all(|(time, local_time_type)| {
+                let offset = tz.offset_from_utc_datetime(&time.naive_utc());
+                offset.fix().local_minus_utc() == local_time_type.utc_offset
+                    && offset.abbreviation() == Some(&local_time_type.abbreviation[..])
+            })
+        })
+    }
+
+    fn parse_data(data: &[u8], header: &Header, time_size: usize) -> SvLogResult<Self> {
+        // NOTE: all offsets below are within the data once its length is
+        // checked
+        ensure!(
+            header.typecnt > 0
+                && matches!(header.data_len(time_size), Some(len) if data.len() >= len),
+            TimeZoneSnafu {
+                message: "Invalid or truncated TZif data".to_string(),
+            }
+        );
+        let indices_start = header.timecnt * time_size;
+        let types_start = indices_start + header.timecnt;
+        let chars_start = types_start + header.typecnt * 6;
+        let chars = &data[chars_start..chars_start + header.charcnt];
+        let transitions = (0..header.timecnt)
+            .map(|i| {
+                let time = read_int(data, i * time_size, time_size);
+                let index = usize::from(data[indices_start + i]);
+                (time, index.min(header.typecnt - 1))
+            })
+            .collect();
+        let local_time_types = (0..header.typecnt)
+            .map(|i| {
+                let pos = types_start + i * 6;
+                let abbreviation_start = usize::from(data[pos + 5]).min(chars.len());
+                let abbreviation = chars[abbreviation_start..]
+                    .split(|c| *c == 0)
+                    .next()
+                    .unwrap_or(&[]);
+                LocalTimeType {
+                    utc_offset: read_int(data, pos, 4) as i32,
+                    abbreviation: String::from_utf8_lossy(abbreviation).to_string(),
+                }
+            })
+            .collect();
+        Ok(Tzif {
+            transitions,
+            local_time_types,
+        })
+    }
+}
+
+// Reads a big-endian signed integer of 4 or 8 bytes.
+fn read_int(data: &[u8], pos: usize, size: usize) -> i64 {
+    let bytes = &data[pos..pos + size];
+    if size == 4 {
+        i64::from(i32::from_be_bytes(bytes.try_into().unwrap()))
+    } else {
+        i64::from_be_bytes(bytes.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ZURICH: &[u8] = include_bytes!("../tests/fixtures/Europe_Zurich");
+    static NEW_YORK: &[u8] = include_bytes!("../tests/fixtures/America_New_York");
+    static UTC: &[u8] = include_bytes!("../tests/fixtures/Etc_UTC");
+
+    #[test]
+    fn parse_zurich() {
+        let tzif = Tzif::parse(ZURICH).unwrap();
+        // 1995-07-01T00:00:00Z
+        let summer = tzif.local_time_type(804556800);
+        assert_eq!(summer.utc_offset, 7200);
+        assert_eq!(summer.abbreviation, "CEST");
+        // 1995-12-01T00:00:00Z
+        let winter = tzif.local_time_type(817776000);
+        assert_eq!(winter.utc_offset, 3600);
+        assert_eq!(winter.abbreviation, "CET");
+    }
+
+    #[test]
+    fn find_zurich() {
+        let tz = Tzif::parse(ZURICH).unwrap().find_tz().unwrap();
+        // NOTE: the first alias in alphabetical order
+        assert_eq!(tz, Tz::Europe__Busingen);
+    }
+
+    #[test]
+    fn find_new_york() {
+        let tz = Tzif::parse(NEW_YORK).unwrap().find_tz().unwrap();
+        assert_eq!(tz, Tz::America__New_York);
+    }
+
+    #[test]
+    fn find_utc() {
+        let tz = Tzif::parse(UTC).unwrap().find_tz().unwrap();
+        assert_eq!(
+            tz.offset_from_utc_datetime(&Utc::now().naive_utc())
+                .abbreviation(),
+            Some("UTC")
+        );
+    }
+
+    #[test]
+    fn parse_err_invalid_magic() {
+        assert!(Tzif::parse(b"TZxx").is_err());
+    }
+
+    #[test]
+    fn parse_err_truncated() {
+        assert!(Tzif::parse(&ZURICH[..100]).is_err());
+    }
+
+    #[test]
+    fn parse_err_huge_counts() {
+        for version in [0, b'2'] {
+            let mut data = ZURICH[..HEADER_LEN].to_vec();
+            data[4] = version;
+            data[20..44].fill(0xff);
+            assert!(Tzif::parse(&data).is_err());
+        }
+    }
+}
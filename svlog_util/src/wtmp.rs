@@ -0,0 +1,184 @@
+use std::{ops::Sub, path::Path};
+
+use chrono::{DateTime, Duration};
+use snafu::ResultExt;
+
+use crate::{
+    os_times::Boot,
+    svlog_error::{OpenFileSnafu, SvLogResult},
+};
+
+// Reader for the boot records in wtmp files. The records have the layout of
+// `struct utmp`, which is the same for glibc on all platforms and for musl
+// before 1.2 on 32 bit platforms (e.g. on Void Linux):
+//
+// offset  size  field
+//      0     2  ut_type
+//      4     4  ut_pid
+//      8    32  ut_line
+//     40     4  ut_id
+//     44    32  ut_user
+//     76   256  ut_host
+//    332     4  ut_exit
+//    336     4  ut_session
+//    340     4  ut_tv.tv_sec
+//    344     4  ut_tv.tv_usec
+//    348    36  ut_addr_v6, unused
+//
+// On 64 bit platforms (and with the 64 bit times of musl 1.2 on 32 bit
+// platforms, which aren't supported) musl has 8 byte fields for ut_session
+// (or ut_session with padding since musl 1.2) and ut_tv:
+//
+// offset  size  field
+//    336     8  ut_session
+//    344     8  ut_tv.tv_sec
+//    352     8  ut_tv.tv_usec
+//    360    40  ut_addr_v6, unused
+
+// Size of the records and offset and size of ut_tv.tv_sec
+struct Layout {
+    size: usize,
+    tv_sec: usize,
+    time64: bool,
+}
+
+const GLIBC_LAYOUT: Layout = Layout {
+    size: 384,
+    tv_sec: 340,
+    time64: false,
+};
+const MUSL_64_LAYOUT: Layout = Layout {
+    size: 400,
+    tv_sec: 344,
+    time64: true,
+};
+const LAYOUT: Layout = if cfg!(all(target_env = "musl", target_pointer_width = "64")) {
+    MUSL_64_LAYOUT
+} else {
+    GLIBC_LAYOUT
+};
+
+static RUN_LVL: i16 = 1;
+static BOOT_TIME: i16 = 2;
+
+// Reads all boots from the wtmp file at path, ordered from the current (i.e.
+// last) boot to the first one. Boots without shutdown record (e.g. crashes)
+// end one second before the next boot.
+pub fn read_wtmp_boots(path: &Path) -> SvLogResult<Vec<Boot>> {
+    let data = std::fs::read(path).context(OpenFileSnafu {
+        path: format!("{:?}", path),
+    })?;
+    Ok(parse_wtmp_boots(&data, &LAYOUT))
+}
+
+fn parse_wtmp_boots(data: &[u8], layout: &Layout) -> Vec<Boot> {
+    let mut boots: Vec<Boot> = Vec::new();
+    for record in data.chunks_exact(layout.size) {
+        let ut_type = i16::from_ne_bytes([record[0], record[1]]);
+        let user = record[44..76].split(|c| *c == 0).next().unwrap_or(&[]);
+        let tv_sec = &record[layout.tv_sec..];
+        let seconds = if layout.time64 {
+            i64::from_ne_bytes(tv_sec[..8].try_into().unwrap())
+        } else {
+            i64::from(i32::from_ne_bytes(tv_sec[..4].try_into().unwrap()))
+        };
+        let time = match DateTime::from_timestamp(seconds, 0) {
+            Some(time) => time.naive_utc(),
+            None => continue,
+        };
+        if ut_type == BOOT_TIME {
+            if let Some(previous) = boots.last_mut() {
+                previous.end.get_or_insert(time.sub(Duration::seconds(1)));
+            }
+            boots.push(Boot {
+                start: time,
+                end: None,
+            });
+        } else if ut_type == RUN_LVL && user == b"shutdown" {
+            if let Some(previous) = boots.last_mut() {
+                previous.end.get_or_insert(time);
+            }
+        }
+    }
+    boots.reverse();
+    boots
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    static WTMP: &[u8] = include_bytes!("../tests/fixtures/wtmp");
+
+    fn ndt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    // Builds a record with the layout of musl on 64 bit platforms.
+    fn musl_64_record(ut_type: i16, user: &str, seconds: i64) -> Vec<u8> {
+        let mut record = vec![0; MUSL_64_LAYOUT.size];
+        record[..2].copy_from_slice(&ut_type.to_ne_bytes());
+        record[44..44 + user.len()].copy_from_slice(user.as_bytes());
+        record[344..352].copy_from_slice(&seconds.to_ne_bytes());
+        record
+    }
+
+    #[test]
+    fn boots() {
+        let boots = parse_wtmp_boots(WTMP, &GLIBC_LAYOUT);
+        assert_eq!(
+            boots,
+            vec![
+                Boot {
+                    start: ndt("2022-09-03 08:00:00"),
+                    end: None,
+                },
+                Boot {
+                    start: ndt("2022-09-02 13:30:00"),
+                    end: Some(ndt("2022-09-03 07:59:59")),
+                },
+                Boot {
+                    start: ndt("2022-09-01 13:30:00"),
+                    end: Some(ndt("2022-09-01 13:40:00")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_record() {
+        let boots = parse_wtmp_boots(&WTMP[..GLIBC_LAYOUT.size + 100], &GLIBC_LAYOUT);
+        assert_eq!(boots.len(), 1);
+    }
+
+    #[test]
+    fn musl_64_bit() {
+        let data = [
+            musl_64_record(BOOT_TIME, "reboot", 1662039000),
+            musl_64_record(RUN_LVL, "shutdown", 1662039600),
+            musl_64_record(BOOT_TIME, "reboot", 1662125400),
+        ]
+        .concat();
+        let boots = parse_wtmp_boots(&data, &MUSL_64_LAYOUT);
+        assert_eq!(
+            boots,
+            vec![
+                Boot {
+                    start: ndt("2022-09-02 13:30:00"),
+                    end: None,
+                },
+                Boot {
+                    start: ndt("2022-09-01 13:30:00"),
+                    end: Some(ndt("2022-09-01 13:40:00")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert!(parse_wtmp_boots(&[], &LAYOUT).is_empty());
+    }
+}